@@ -0,0 +1,39 @@
+//! How fast swipe updates go through the state machine.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use gestora::gesture::{RecognitionConfig, SwipeStateMachine};
+use std::hint::black_box;
+
+const UPDATES: usize = 1000;
+
+/// Updates of a swipe that drifts east and then curves north.
+fn curved_swipe() -> Vec<(f64, f64)> {
+    (0..UPDATES)
+        .map(|i| {
+            let t = i as f64 / UPDATES as f64;
+            (2.0 * (1.0 - t), -2.0 * t)
+        })
+        .collect()
+}
+
+fn classify(c: &mut Criterion) {
+    let updates = curved_swipe();
+    let mut group = c.benchmark_group("classify");
+    group.throughput(Throughput::Elements(UPDATES as u64));
+
+    group.bench_function("swipe", |b| {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig::default());
+        b.iter(|| {
+            state_machine.begin(3);
+            for &(dx, dy) in &updates {
+                state_machine.update(black_box(dx), black_box(dy));
+            }
+            black_box(state_machine.end())
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, classify);
+criterion_main!(benches);
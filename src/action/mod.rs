@@ -0,0 +1,921 @@
+mod template;
+
+use crate::config::{Config, Trigger, WorkspaceRange};
+use gestora::gesture::{Pinch, PinchDir, Swipe};
+use gestora::sway::{Sway, SwayError, Workspace};
+use log::{LevelFilter, debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Action {
+    /// Moves back as many workspaces as the swipe has steps.
+    PrevWorkspace,
+    /// Moves forward as many workspaces as the swipe has steps.
+    NextWorkspace,
+    /// Jumps to the first workspace of the output's range.
+    FirstWorkspace,
+    /// Jumps to the last workspace of the output's range.
+    LastWorkspace,
+    /// Jumps to the lowest numbered workspace of the output's range that
+    /// doesn't exist yet.
+    NextEmptyWorkspace,
+    /// Jumps to the first workspace of the previous workspace group.
+    PrevGroup,
+    /// Jumps to the first workspace of the next workspace group.
+    NextGroup,
+    /// Focuses the most recent urgent window, if there is one.
+    FocusUrgent,
+    /// Closes the focused window.
+    Kill,
+    /// Closes the focused window on the second swipe within this many
+    /// milliseconds, so a single stray swipe can't close anything.
+    ConfirmKill(u64),
+    /// Moves the focused window to the next output in `direction`, and
+    /// focus along with it if `follow` is set.
+    MoveToOutput {
+        direction: OutputDirection,
+        #[serde(default)]
+        follow: bool,
+    },
+    /// Focuses the next output in `direction`. At the last output that way,
+    /// `wrap` goes around to the output at the other end instead of staying.
+    FocusOutput {
+        direction: OutputDirection,
+        #[serde(default)]
+        wrap: bool,
+    },
+    /// Moves the workspace shown on the output that isn't focused like
+    /// `prev-workspace` or `next-workspace`, and keeps the focus where it is.
+    /// With more than two outputs, `output` names the one to drive.
+    OtherOutputWorkspace {
+        step: Step,
+        #[serde(default)]
+        output: Option<String>,
+    },
+    /// Trades the focused workspace for the one shown on the output that
+    /// isn't focused, keeping the focus on the same output.
+    SwapWorkspaces,
+    /// Switches the split of the focused container between horizontal and
+    /// vertical.
+    SplitToggle,
+    /// Splits the focused container horizontally.
+    SplitHorizontal,
+    /// Splits the focused container vertically.
+    SplitVertical,
+    /// Switches the focused window between floating and tiling.
+    FloatingToggle,
+    /// Grows the inner gaps of the focused workspace by `delta` pixels. A
+    /// pinch in shrinks them instead, which sway stops at zero.
+    Gaps { delta: u32 },
+    /// Focuses the window with this mark.
+    FocusMark(String),
+    /// Focuses the next marked window each time it runs.
+    NextMark,
+    /// Focuses the next window in tree order each time it runs, going on from
+    /// the last one it focused until a swipe runs other actions.
+    CycleWindows,
+    /// Reloads sway's config.
+    ReloadCompositor,
+    /// Switches gestora's own logging between info and debug.
+    ToggleDebugLog,
+    /// Switches sway to the binding mode with this name, see
+    /// `mode_timeout_ms` for going back to the default one.
+    SetMode(String),
+    /// Takes back the last workspace switch or window move that hasn't been
+    /// undone yet.
+    Undo,
+    /// Runs the config's `launcher` command. Resolved to an [`Action::Exec`]
+    /// when the config is compiled.
+    Launcher,
+    /// Sends the config's `show_desktop` command to sway. Resolved to an
+    /// [`Action::Command`] when the config is compiled.
+    ShowDesktop,
+    /// Sends a command to sway.
+    Command(String),
+    /// Sends a command to sway with `$value` between `min` and `max`, in
+    /// proportion to how far the swipe went up to `distance`.
+    Scaled {
+        command: String,
+        min: f64,
+        max: f64,
+        #[serde(default = "default_scaled_distance")]
+        distance: f64,
+    },
+    /// Broadcasts a tick with this payload to sway IPC clients subscribed to
+    /// tick events, such as a status bar.
+    Tick(String),
+    /// Shows a desktop notification through `notify-send`.
+    Notify {
+        summary: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        body: Option<String>,
+    },
+    /// Shows the workspaces in a notification, with the focused one in
+    /// brackets.
+    ListWorkspaces,
+    /// Runs a shell command, or a program with arguments.
+    Exec(ExecCommand),
+}
+
+/// What an [`Action::Exec`] runs.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum ExecCommand {
+    /// Runs through `sh -c`. The swipe direction and finger count are passed
+    /// as `$1` and `$2`.
+    Shell(String),
+    /// Runs the program in the first element with the rest as arguments,
+    /// without a shell to get the quoting wrong.
+    Argv(Vec<String>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum OutputDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Step {
+    Prev,
+    Next,
+}
+
+impl OutputDirection {
+    fn name(&self) -> &'static str {
+        match self {
+            OutputDirection::Left => "left",
+            OutputDirection::Right => "right",
+            OutputDirection::Up => "up",
+            OutputDirection::Down => "down",
+        }
+    }
+
+    fn opposite(&self) -> OutputDirection {
+        match self {
+            OutputDirection::Left => OutputDirection::Right,
+            OutputDirection::Right => OutputDirection::Left,
+            OutputDirection::Up => OutputDirection::Down,
+            OutputDirection::Down => OutputDirection::Up,
+        }
+    }
+}
+
+/// How far a swipe has to go for an [`Action::Scaled`] to reach `max`.
+const SCALED_DISTANCE: f64 = 1000.0;
+
+fn default_scaled_distance() -> f64 {
+    SCALED_DISTANCE
+}
+
+/// Maps `distance` linearly from 0 to `full` onto `min` to `max`, rounded to
+/// two decimals so it reads well in a command.
+fn scale(distance: f64, full: f64, min: f64, max: f64) -> f64 {
+    let fraction = (distance / full).clamp(0.0, 1.0);
+    ((min + (max - min) * fraction) * 100.0).round() / 100.0
+}
+
+/// Starts `command` without waiting for it, reaping it on a separate thread.
+/// Failing to start is logged rather than treated as a daemon failure.
+fn spawn_detached(mut command: Command) {
+    match command.spawn() {
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => warn!("Failed to run {:?}: {}", command.get_program(), err),
+    }
+}
+
+/// The gesture actions run for.
+#[derive(Clone, Copy)]
+pub(crate) enum Gesture<'a> {
+    Swipe(&'a Swipe),
+    Pinch(&'a Pinch),
+}
+
+impl Gesture<'_> {
+    pub fn device(&self) -> Option<&str> {
+        match self {
+            Gesture::Swipe(swipe) => swipe.device.as_deref(),
+            Gesture::Pinch(pinch) => pinch.device.as_deref(),
+        }
+    }
+
+    pub fn finger_count(&self) -> i32 {
+        match self {
+            Gesture::Swipe(swipe) => swipe.finger_count,
+            Gesture::Pinch(pinch) => pinch.finger_count,
+        }
+    }
+
+    /// How many times in a row the gesture was made, 1 for pinches.
+    pub fn repeat_count(&self) -> usize {
+        match self {
+            Gesture::Swipe(swipe) => swipe.repeat_count,
+            Gesture::Pinch(_) => 1,
+        }
+    }
+
+    /// What bindings for this gesture match on besides the finger count.
+    pub fn trigger(&self) -> Trigger {
+        match self {
+            Gesture::Swipe(swipe) => swipe.dir.into(),
+            Gesture::Pinch(pinch) => pinch.dir.into(),
+        }
+    }
+
+    /// The direction, as named in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gesture::Swipe(swipe) => swipe.dir.name(),
+            Gesture::Pinch(pinch) => pinch.dir.name(),
+        }
+    }
+
+    /// How many workspaces workspace actions move. Pinches always move one.
+    fn steps(&self) -> usize {
+        match self {
+            Gesture::Swipe(swipe) => swipe.steps(),
+            Gesture::Pinch(_) => 1,
+        }
+    }
+
+    /// How far the fingers travelled. Pinches report none.
+    fn distance(&self) -> f64 {
+        match self {
+            Gesture::Swipe(swipe) => swipe.distance,
+            Gesture::Pinch(_) => 0.0,
+        }
+    }
+}
+
+/// How many actions [`Action::Undo`] can take back.
+const UNDO_HISTORY: usize = 16;
+
+/// How to take back what an action did.
+#[derive(Debug)]
+enum Undo {
+    /// Go back to this workspace.
+    Workspace(usize),
+    /// Send this command to sway.
+    Command(String),
+}
+
+/// What actions remember between swipes.
+#[derive(Default)]
+pub(crate) struct ActionState {
+    /// How to take back the latest actions, the most recent last.
+    history: Vec<Undo>,
+    /// Until when a [`Action::ConfirmKill`] waits for the confirming swipe.
+    kill_armed_until: Option<Instant>,
+    /// How long the mode of an [`Action::SetMode`] lasts without gestures,
+    /// and until when it lasts now.
+    mode_timeout: Option<(Duration, Instant)>,
+    /// The mark [`Action::NextMark`] focused last.
+    last_mark: Option<String>,
+    /// The window [`Action::CycleWindows`] focused last.
+    last_cycled: Option<i64>,
+    /// The workspace a preview started from, and the device of its swipe.
+    preview_origin: Option<(usize, Option<String>)>,
+}
+
+impl ActionState {
+    /// Called with the actions of each swipe once they ran. Any other swipe
+    /// interrupts [`Action::CycleWindows`], which then starts over from the
+    /// focused window.
+    pub fn end_swipe(&mut self, actions: &[Action]) {
+        if !actions
+            .iter()
+            .any(|action| matches!(action, Action::CycleWindows))
+        {
+            self.last_cycled = None;
+        }
+    }
+
+    /// Goes back to the workspace a preview started from.
+    pub fn cancel_preview(&mut self, sway: &mut Sway) -> Result<(), SwayError> {
+        let Some((origin, device)) = self.preview_origin.take() else {
+            return Ok(());
+        };
+
+        if sway.get_seat_workspace(device.as_deref())? != origin {
+            sway.set_active_workspace(origin)?;
+        }
+        Ok(())
+    }
+
+    /// Forgets the preview once its swipe ran, even if it ran no workspace
+    /// action.
+    pub fn end_preview(&mut self) {
+        self.preview_origin = None;
+    }
+
+    /// When to go back to the default mode, if a gesture doesn't come first.
+    pub fn mode_deadline(&self) -> Option<Instant> {
+        self.mode_timeout.map(|(_, until)| until)
+    }
+
+    /// Called for every gesture, which keeps the mode of an
+    /// [`Action::SetMode`] going.
+    pub fn keep_mode(&mut self) {
+        if let Some((timeout, until)) = &mut self.mode_timeout {
+            *until = Instant::now() + *timeout;
+        }
+    }
+
+    /// Goes back to the default mode once the mode's timeout ran out.
+    pub fn leave_mode(&mut self, sway: &mut Sway) -> Result<(), SwayError> {
+        if self.mode_timeout.take().is_some() {
+            debug!("No gestures for a while, going back to the default mode");
+            sway.run_command("mode default")?;
+        }
+        Ok(())
+    }
+
+    fn remember(&mut self, undo: Undo) {
+        if self.history.len() == UNDO_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(undo);
+    }
+}
+
+impl Action {
+    /// The strings of this action that may contain `$name` placeholders.
+    fn templates(&self) -> Vec<&str> {
+        match self {
+            Action::Command(command)
+            | Action::Scaled { command, .. }
+            | Action::Tick(command)
+            | Action::Exec(ExecCommand::Shell(command)) => vec![command],
+            Action::Exec(ExecCommand::Argv(argv)) => argv.iter().map(String::as_str).collect(),
+            Action::Notify { summary, body } => std::iter::once(summary)
+                .chain(body)
+                .map(String::as_str)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the first placeholder this action uses that isn't known.
+    pub fn unknown_placeholder(&self) -> Option<&str> {
+        let scaled = matches!(self, Action::Scaled { .. });
+        self.templates().into_iter().find_map(|command| {
+            if !scaled && template::uses(command, "value") {
+                return Some("value");
+            }
+            template::unknown_placeholder(command)
+        })
+    }
+
+    /// The workspace a workspace action goes to from `from`.
+    fn workspace_target(
+        &self,
+        sway: &Sway,
+        gesture: Gesture,
+        config: &Config,
+        from: usize,
+    ) -> Result<usize, SwayError> {
+        let range = workspace_range(sway, config, from)?;
+        Ok(match self {
+            Action::FirstWorkspace => range.first_workspace,
+            Action::LastWorkspace => range.last_workspace,
+            Action::PrevGroup | Action::NextGroup => config
+                .adjacent_group(from, matches!(self, Action::NextGroup))
+                .unwrap_or(from),
+            _ => range.step(from, gesture.steps(), matches!(self, Action::NextWorkspace)),
+        })
+    }
+
+    /// Shows what the action would do while the fingers are still down.
+    /// Only workspace actions have a preview: they switch to the target
+    /// workspace, and back when the preview is cancelled.
+    pub fn preview(
+        &self,
+        sway: &mut Sway,
+        gesture: Gesture,
+        config: &Config,
+        state: &mut ActionState,
+    ) -> Result<(), SwayError> {
+        if !matches!(
+            self,
+            Action::PrevWorkspace
+                | Action::NextWorkspace
+                | Action::FirstWorkspace
+                | Action::LastWorkspace
+                | Action::PrevGroup
+                | Action::NextGroup
+        ) {
+            return Ok(());
+        }
+
+        let active_workspace = sway.get_seat_workspace(gesture.device())?;
+        let (origin, _) = state
+            .preview_origin
+            .get_or_insert_with(|| (active_workspace, gesture.device().map(str::to_string)));
+        let target = self.workspace_target(sway, gesture, config, *origin)?;
+
+        if target != active_workspace {
+            sway.set_active_workspace(target)?;
+        }
+        Ok(())
+    }
+
+    pub fn run(
+        &self,
+        sway: &mut Sway,
+        gesture: Gesture,
+        config: &Config,
+        state: &mut ActionState,
+    ) -> Result<(), SwayError> {
+        match self {
+            Action::PrevWorkspace
+            | Action::NextWorkspace
+            | Action::FirstWorkspace
+            | Action::LastWorkspace
+            | Action::PrevGroup
+            | Action::NextGroup => {
+                let active_workspace = sway.get_seat_workspace(gesture.device())?;
+                // A preview already moved, so count from where it started.
+                let from = state
+                    .preview_origin
+                    .take()
+                    .map_or(active_workspace, |(origin, _)| origin);
+                let target = self.workspace_target(sway, gesture, config, from)?;
+
+                if target != active_workspace {
+                    sway.set_active_workspace(target)?;
+                }
+                if target != from {
+                    state.remember(Undo::Workspace(from));
+                }
+
+                Ok(())
+            }
+            Action::NextEmptyWorkspace => {
+                let active_workspace = sway.get_seat_workspace(gesture.device())?;
+                let range = workspace_range(sway, config, active_workspace)?;
+                let used: Vec<usize> = sway
+                    .get_workspaces()?
+                    .into_iter()
+                    .filter_map(|workspace| usize::try_from(workspace.num).ok())
+                    .collect();
+
+                match range.first_unused(&used) {
+                    Some(target) => {
+                        sway.set_active_workspace(target)?;
+                        state.remember(Undo::Workspace(active_workspace));
+                        Ok(())
+                    }
+                    None => {
+                        debug!("Every workspace up to {} is in use", range.last_workspace);
+                        Ok(())
+                    }
+                }
+            }
+            Action::FocusUrgent => match sway.run_command("[urgent=latest] focus") {
+                // Sway reports an error when no window matches the criteria.
+                Err(SwayError::IpcError(err)) => {
+                    debug!("No urgent window to focus: {}", err);
+                    Ok(())
+                }
+                result => result,
+            },
+            Action::Kill => sway.run_command("kill"),
+            Action::Gaps { delta } => {
+                let operation = match gesture {
+                    Gesture::Pinch(Pinch {
+                        dir: PinchDir::In, ..
+                    }) => "minus",
+                    _ => "plus",
+                };
+                sway.run_command(&format!("gaps inner current {} {}", operation, delta))
+            }
+            Action::ConfirmKill(timeout_ms) => {
+                let now = Instant::now();
+                match state.kill_armed_until.take() {
+                    Some(until) if now <= until => sway.run_command("kill"),
+                    _ => {
+                        info!("Swipe again within {} ms to close the window", timeout_ms);
+                        state.kill_armed_until = Some(now + Duration::from_millis(*timeout_ms));
+                        Ok(())
+                    }
+                }
+            }
+            Action::MoveToOutput { direction, follow } => {
+                let mut command = format!("move container to output {}", direction.name());
+                if *follow {
+                    command.push_str(&format!(", focus output {}", direction.name()));
+                }
+
+                // Moving the window back needs to know which one it was and
+                // where it came from.
+                let tree = sway.get_tree()?;
+                let origin = tree
+                    .focused_node()
+                    .filter(|node| node.node_type.ends_with("con"))
+                    .map(|window| window.id)
+                    .zip(tree.focused_output().and_then(|output| output.name.clone()));
+
+                match sway.run_command(&command) {
+                    // Sway reports an error when there is no output that way.
+                    Err(SwayError::IpcError(err)) => {
+                        debug!("Not moving to output {}: {}", direction.name(), err);
+                        Ok(())
+                    }
+                    Ok(()) => {
+                        if let Some((id, output)) = origin {
+                            let mut undo =
+                                format!("[con_id={}] move container to output {}", id, output);
+                            if *follow {
+                                undo.push_str(&format!("; focus output {}", output));
+                            }
+                            state.remember(Undo::Command(undo));
+                        }
+                        Ok(())
+                    }
+                    result => result,
+                }
+            }
+            Action::FocusOutput { direction, wrap } => {
+                match sway.run_command(&format!("focus output {}", direction.name())) {
+                    // Sway reports an error when there is no output that way.
+                    Err(SwayError::IpcError(err)) if *wrap => {
+                        debug!("Wrapping around: {}", err);
+                        match far_output(sway, direction.opposite())? {
+                            Some(name) => sway.run_command(&format!("focus output {}", name)),
+                            None => Ok(()),
+                        }
+                    }
+                    Err(SwayError::IpcError(err)) => {
+                        debug!("Not focusing output {}: {}", direction.name(), err);
+                        Ok(())
+                    }
+                    result => result,
+                }
+            }
+            Action::OtherOutputWorkspace { step, output } => {
+                let workspaces = sway.get_workspaces()?;
+                let focused = workspaces
+                    .iter()
+                    .find(|workspace| workspace.focused)
+                    .ok_or(SwayError::NoFocusedWorkspace)?;
+                let Some(other) = other_output(sway, &focused.output, output.as_deref())? else {
+                    return Ok(());
+                };
+                let Some(shown) = workspaces
+                    .iter()
+                    .find(|workspace| workspace.visible && workspace.output == other)
+                    .and_then(|workspace| usize::try_from(workspace.num).ok())
+                else {
+                    debug!("{} shows no numbered workspace", other);
+                    return Ok(());
+                };
+
+                let range = workspace_range(sway, config, shown)?;
+                let target = range.step(shown, gesture.steps(), matches!(step, Step::Next));
+                if target == shown {
+                    return Ok(());
+                }
+
+                // Switch from the other output so a new workspace is created
+                // there, then give the focus back.
+                let workspace_command = sway.workspace_command().clone();
+                let switch = |workspace| {
+                    format!(
+                        "focus output {}; {}; focus output {}",
+                        other,
+                        workspace_command.command(workspace),
+                        focused.output
+                    )
+                };
+                sway.run_command(&switch(target))?;
+                state.remember(Undo::Command(switch(shown)));
+                Ok(())
+            }
+            Action::SwapWorkspaces => {
+                let workspaces = sway.get_workspaces()?;
+                let focused = workspaces
+                    .iter()
+                    .find(|workspace| workspace.focused)
+                    .ok_or(SwayError::NoFocusedWorkspace)?;
+                let Some(other) = other_output(sway, &focused.output, None)? else {
+                    return Ok(());
+                };
+                let Some(shown) = workspaces
+                    .iter()
+                    .find(|workspace| workspace.visible && workspace.output == other)
+                else {
+                    debug!("{} shows no workspace", other);
+                    return Ok(());
+                };
+
+                // Moving a workspace takes the focus along, so the focused
+                // one goes over first and the other one is fetched back
+                // after it.
+                let swap = |fetch: &str| {
+                    format!(
+                        "move workspace to output {}; workspace --no-auto-back-and-forth \"{}\"; move workspace to output {}",
+                        other, fetch, focused.output
+                    )
+                };
+                sway.run_command(&swap(&shown.name))?;
+                state.remember(Undo::Command(swap(&focused.name)));
+                Ok(())
+            }
+            Action::SplitToggle => sway.run_command("split toggle"),
+            Action::SplitHorizontal => sway.run_command("split horizontal"),
+            Action::SplitVertical => sway.run_command("split vertical"),
+            Action::FloatingToggle => match sway.run_command("floating toggle") {
+                // Sway reports an error for containers that can't float.
+                Err(SwayError::IpcError(err)) => {
+                    warn!("Can't toggle floating: {}", err);
+                    Ok(())
+                }
+                result => result,
+            },
+            Action::FocusMark(mark) => focus_mark(sway, mark),
+            Action::NextMark => {
+                let marks = sway.get_marks()?;
+                let next = state
+                    .last_mark
+                    .as_ref()
+                    .and_then(|last| marks.iter().position(|mark| mark == last))
+                    .map_or(0, |index| (index + 1) % marks.len());
+
+                let Some(mark) = marks.get(next) else {
+                    debug!("No marked windows to focus");
+                    return Ok(());
+                };
+
+                focus_mark(sway, mark)?;
+                state.last_mark = Some(mark.clone());
+                Ok(())
+            }
+            Action::CycleWindows => {
+                let tree = sway.get_tree()?;
+                let windows = tree.windows();
+                let current = state.last_cycled.or_else(|| {
+                    windows
+                        .iter()
+                        .find(|window| window.focused)
+                        .map(|window| window.id)
+                });
+                let next = current
+                    .and_then(|id| windows.iter().position(|window| window.id == id))
+                    .map_or(0, |index| (index + 1) % windows.len());
+
+                let Some(window) = windows.get(next) else {
+                    debug!("No windows to cycle through");
+                    return Ok(());
+                };
+
+                sway.run_command(&format!("[con_id={}] focus", window.id))?;
+                state.last_cycled = Some(window.id);
+                Ok(())
+            }
+            Action::SetMode(name) => {
+                sway.run_command(&format!("mode \"{}\"", name))?;
+                state.mode_timeout =
+                    config
+                        .mode_timeout_ms
+                        .filter(|_| name != "default")
+                        .map(|timeout_ms| {
+                            let timeout = Duration::from_millis(timeout_ms);
+                            (timeout, Instant::now() + timeout)
+                        });
+                Ok(())
+            }
+            Action::Undo => match state.history.pop() {
+                Some(Undo::Workspace(workspace)) => {
+                    if sway.get_seat_workspace(gesture.device())? != workspace {
+                        sway.set_active_workspace(workspace)?;
+                    }
+                    Ok(())
+                }
+                Some(Undo::Command(command)) => match sway.run_command(&command) {
+                    // The window may be gone by now.
+                    Err(SwayError::IpcError(err)) => {
+                        debug!("Can't undo {:?}: {}", command, err);
+                        Ok(())
+                    }
+                    result => result,
+                },
+                None => {
+                    debug!("Nothing to undo");
+                    Ok(())
+                }
+            },
+            Action::ReloadCompositor => sway.run_command("reload"),
+            Action::ToggleDebugLog => {
+                let level = if log::max_level() >= LevelFilter::Debug {
+                    LevelFilter::Info
+                } else {
+                    LevelFilter::Debug
+                };
+
+                log::set_max_level(level);
+                info!("Logging at {} level", level);
+                Ok(())
+            }
+            Action::Launcher | Action::ShowDesktop => {
+                debug!("{:?} has no command configured", self);
+                Ok(())
+            }
+            Action::Command(command) => {
+                let command = expand(command, sway, gesture)?;
+                sway.run_command(&command)
+            }
+            Action::Scaled {
+                command,
+                min,
+                max,
+                distance,
+            } => {
+                let value = scale(gesture.distance(), *distance, *min, *max);
+                let command = expand_with(command, sway, gesture, Some(value))?;
+                sway.run_command(&command)
+            }
+            Action::Tick(payload) => {
+                let payload = expand(payload, sway, gesture)?;
+                sway.send_tick(&payload)
+            }
+            Action::Notify { summary, body } => {
+                let mut command = Command::new("notify-send");
+                command.arg(expand(summary, sway, gesture)?);
+                if let Some(body) = body {
+                    command.arg(expand(body, sway, gesture)?);
+                }
+
+                spawn_detached(command);
+                Ok(())
+            }
+            Action::ListWorkspaces => {
+                let list = format_workspaces(&sway.get_workspaces()?);
+                info!("Workspaces: {}", list);
+
+                let mut command = Command::new("notify-send");
+                command.arg("Workspaces").arg(list);
+                spawn_detached(command);
+                Ok(())
+            }
+            Action::Exec(ExecCommand::Shell(script)) => {
+                let mut command = Command::new("sh");
+                command
+                    .arg("-c")
+                    .arg(expand(script, sway, gesture)?)
+                    .arg("gestora")
+                    .arg(gesture.name())
+                    .arg(gesture.finger_count().to_string());
+
+                spawn_detached(command);
+                Ok(())
+            }
+            Action::Exec(ExecCommand::Argv(argv)) => {
+                // Config loading rejects an empty argv.
+                let mut argv = argv.iter().map(|arg| expand(arg, sway, gesture));
+                let mut command = Command::new(argv.next().unwrap()?);
+                for arg in argv {
+                    command.arg(arg?);
+                }
+
+                spawn_detached(command);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The workspaces swipes from `workspace` stay within: its group, or else the
+/// range of the output showing it.
+fn workspace_range(
+    sway: &Sway,
+    config: &Config,
+    workspace: usize,
+) -> Result<WorkspaceRange, SwayError> {
+    let mut range = if config.has_output_ranges() {
+        sway.get_workspaces()?
+            .into_iter()
+            .find(|candidate| usize::try_from(candidate.num) == Ok(workspace))
+            .map(|workspace| config.workspace_range(&workspace.output))
+            .unwrap_or_default()
+    } else {
+        WorkspaceRange::default()
+    };
+
+    // Within a group, swipes stay in the group.
+    if let Some(group) = config.workspace_group(workspace) {
+        range.first_workspace = group.first_workspace;
+        range.last_workspace = group.last_workspace;
+    }
+    Ok(range)
+}
+
+/// The output an [`Action::OtherOutputWorkspace`] or
+/// [`Action::SwapWorkspaces`] goes to: `configured`, or else the only active
+/// output besides `focused`.
+fn other_output(
+    sway: &Sway,
+    focused: &str,
+    configured: Option<&str>,
+) -> Result<Option<String>, SwayError> {
+    if let Some(output) = configured {
+        return Ok((output != focused).then(|| output.to_string()));
+    }
+
+    let others: Vec<String> = sway
+        .get_outputs()?
+        .into_iter()
+        .filter(|output| output.active && output.name != focused)
+        .map(|output| output.name)
+        .collect();
+    match others.as_slice() {
+        [other] => Ok(Some(other.clone())),
+        [] => Ok(None),
+        _ => {
+            warn!(
+                "There are {} other outputs, not picking one of them",
+                others.len()
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// The active output furthest in `direction`.
+fn far_output(sway: &Sway, direction: OutputDirection) -> Result<Option<String>, SwayError> {
+    let outputs = sway.get_outputs()?;
+    let active = outputs.into_iter().filter(|output| output.active);
+    let output = match direction {
+        OutputDirection::Left => active.min_by_key(|output| output.rect.x),
+        OutputDirection::Right => active.max_by_key(|output| output.rect.x),
+        OutputDirection::Up => active.min_by_key(|output| output.rect.y),
+        OutputDirection::Down => active.max_by_key(|output| output.rect.y),
+    };
+
+    Ok(output.map(|output| output.name))
+}
+
+/// Focuses the window marked `mark`. Criteria take a regex, so the mark is
+/// escaped to only match itself.
+fn focus_mark(sway: &mut Sway, mark: &str) -> Result<(), SwayError> {
+    let mut pattern = String::with_capacity(mark.len() + 2);
+    pattern.push('^');
+    for c in mark.chars() {
+        if r#"\.^$|?*+()[]{}""#.contains(c) {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('$');
+
+    sway.run_command(&format!("[con_mark=\"{}\"] focus", pattern))
+}
+
+/// Fills in the placeholders of `template` from the gesture and sway's state.
+fn expand(template: &str, sway: &Sway, gesture: Gesture) -> Result<String, SwayError> {
+    expand_with(template, sway, gesture, None)
+}
+
+/// Like [`expand`], with the `$value` of a scaled command.
+fn expand_with(
+    template: &str,
+    sway: &Sway,
+    gesture: Gesture,
+    value: Option<f64>,
+) -> Result<String, SwayError> {
+    template::expand(template, |name| match name {
+        "workspace" => sway
+            .get_seat_workspace(gesture.device())
+            .map(|num| num.to_string()),
+        "direction" => Ok(gesture.name().to_string()),
+        "fingers" => Ok(gesture.finger_count().to_string()),
+        "distance" => Ok(format!("{:.0}", gesture.distance())),
+        "value" => Ok(value
+            .expect("config loading only allows $value in scaled commands")
+            .to_string()),
+        name => unreachable!("unknown placeholder {}", name),
+    })
+}
+
+/// Lists the workspaces by name, e.g. `1 [2] 3:web`.
+fn format_workspaces(workspaces: &[Workspace]) -> String {
+    workspaces
+        .iter()
+        .map(|workspace| {
+            if workspace.focused {
+                format!("[{}]", workspace.name)
+            } else {
+                workspace.name.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
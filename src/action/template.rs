@@ -0,0 +1,85 @@
+//! `$name` placeholders in command strings.
+//!
+//! `$$` stands for a literal `$`. A `$` that isn't followed by a name, such as
+//! the `$1` positional arguments of exec commands, is left alone.
+
+/// Placeholders that can be used in command strings. `value` only has a
+/// value in scaled commands.
+pub(crate) const PLACEHOLDERS: &[&str] =
+    &["workspace", "direction", "fingers", "distance", "value"];
+
+enum Token<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+fn tokenize(template: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('$') {
+        tokens.push(Token::Text(&rest[..start]));
+        let after = &rest[start + 1..];
+
+        if let Some(escaped) = after.strip_prefix('$') {
+            tokens.push(Token::Text("$"));
+            rest = escaped;
+            continue;
+        }
+
+        if after.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            tokens.push(Token::Placeholder(&after[..end]));
+            rest = &after[end..];
+        } else {
+            tokens.push(Token::Text("$"));
+            rest = after;
+        }
+    }
+
+    tokens.push(Token::Text(rest));
+    tokens
+}
+
+/// Returns the first placeholder in `template` that isn't one of [`PLACEHOLDERS`].
+pub(crate) fn unknown_placeholder(template: &str) -> Option<&str> {
+    tokenize(template)
+        .into_iter()
+        .find_map(|token| match token {
+            Token::Placeholder(name) if !PLACEHOLDERS.contains(&name) => Some(name),
+            _ => None,
+        })
+}
+
+/// Whether `template` uses the placeholder `name`.
+pub(crate) fn uses(template: &str, name: &str) -> bool {
+    tokenize(template)
+        .into_iter()
+        .any(|token| matches!(token, Token::Placeholder(used) if used == name))
+}
+
+/// Replaces every known placeholder with the value `lookup` returns for it.
+/// `lookup` is only called for placeholders that actually appear.
+pub(crate) fn expand<E>(
+    template: &str,
+    mut lookup: impl FnMut(&str) -> Result<String, E>,
+) -> Result<String, E> {
+    let mut expanded = String::with_capacity(template.len());
+
+    for token in tokenize(template) {
+        match token {
+            Token::Text(text) => expanded.push_str(text),
+            Token::Placeholder(name) if PLACEHOLDERS.contains(&name) => {
+                expanded.push_str(&lookup(name)?)
+            }
+            Token::Placeholder(name) => {
+                expanded.push('$');
+                expanded.push_str(name);
+            }
+        }
+    }
+
+    Ok(expanded)
+}
@@ -0,0 +1,110 @@
+use crate::action::{Action, Gesture};
+use log::warn;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long records may sit in the buffer before they are written out.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Appends a line for every gesture to a log file, with the actions the
+/// worker ran for it, e.g. `1728900000.123 device=1267:12345:ELAN fingers=3
+/// direction=east distance=412 velocity=1030 actions=["next-workspace"]`.
+/// Clones write to the same file.
+#[derive(Clone)]
+pub(crate) struct AuditLog(Arc<Mutex<Buffered>>);
+
+struct Buffered {
+    writer: BufWriter<File>,
+    /// When the oldest record still in the buffer was written.
+    pending_since: Option<Instant>,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path) -> io::Result<AuditLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(AuditLog(Arc::new(Mutex::new(Buffered {
+            writer: BufWriter::new(file),
+            pending_since: None,
+        }))))
+    }
+
+    /// When the buffered records are due to be written out.
+    pub fn flush_deadline(&self) -> Option<Instant> {
+        self.0
+            .lock()
+            .unwrap()
+            .pending_since
+            .map(|since| since + FLUSH_INTERVAL)
+    }
+
+    /// Records that `actions` ran for `gesture`.
+    pub fn record(&self, gesture: Gesture, actions: &[Action]) {
+        let mut buffered = self.0.lock().unwrap();
+        if let Err(err) = write_record(&mut buffered.writer, gesture, actions) {
+            warn!("Failed to write the audit log: {}", err);
+        }
+        buffered.pending_since.get_or_insert_with(Instant::now);
+    }
+
+    /// Writes out the buffered records once they are due.
+    pub fn flush(&self) {
+        if self
+            .flush_deadline()
+            .is_none_or(|deadline| Instant::now() < deadline)
+        {
+            return;
+        }
+
+        let mut buffered = self.0.lock().unwrap();
+        buffered.pending_since = None;
+        if let Err(err) = buffered.writer.flush() {
+            warn!("Failed to write the audit log: {}", err);
+        }
+    }
+}
+
+fn write_record(writer: &mut impl Write, gesture: Gesture, actions: &[Action]) -> io::Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let actions = serde_json::to_string(actions)?;
+
+    match gesture {
+        Gesture::Swipe(swipe) => {
+            let usec = |usec: Option<u64>| {
+                usec.map_or_else(|| "unknown".to_string(), |usec| usec.to_string())
+            };
+
+            writeln!(
+                writer,
+                "{:.3} device={} began_usec={} ended_usec={} fingers={} direction={} distance={:.0} duration_ms={} velocity={:.0} confidence={:.2} actions={}",
+                timestamp,
+                swipe.device.as_deref().unwrap_or("unknown"),
+                usec(swipe.began_usec),
+                usec(swipe.ended_usec),
+                swipe.finger_count,
+                swipe.dir.name(),
+                swipe.distance,
+                swipe.duration.as_millis(),
+                swipe.velocity(),
+                swipe.confidence,
+                actions,
+            )
+        }
+        Gesture::Pinch(pinch) => writeln!(
+            writer,
+            "{:.3} device={} fingers={} direction={} scale={:.2} actions={}",
+            timestamp,
+            pinch.device.as_deref().unwrap_or("unknown"),
+            pinch.finger_count,
+            pinch.dir.name(),
+            pinch.scale,
+            actions,
+        ),
+    }
+}
@@ -0,0 +1,1509 @@
+use crate::action::{Action, ExecCommand};
+use crate::{FIRST_WORKSPACE, LAST_WORKSPACE};
+use gestora::gesture::{PinchDir, RecognitionConfig, SwipeDir, Template};
+use gestora::sway::{ByteOrder, Magic, WORKSPACES_TTL, WorkspaceCommand};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error(
+        "{path} is written for config version {version}, but this gestora only understands version {CONFIG_VERSION}; {}",
+        version_hint(*version)
+    )]
+    UnsupportedVersion { path: PathBuf, version: u32 },
+    #[error("Failed to parse {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid finger count '{0}', expected a number, 'N+', 'N-M' or 'any'")]
+    InvalidFingers(String),
+    #[error("Unknown direction '{0}'")]
+    InvalidDirection(String),
+    #[error("Unknown placeholder '${0}'")]
+    UnknownPlaceholder(String),
+    #[error("modified_actions need a binding for an exact finger count, not '{0}'")]
+    ModifierWithoutFingerCount(String),
+    #[error("Output {0} has an empty workspace range")]
+    InvalidWorkspaceRange(String),
+    #[error("Workspace group {0}-{1} is empty or overlaps another group")]
+    InvalidWorkspaceGroup(usize, usize),
+    #[error("exec needs a program to run, not an empty list")]
+    EmptyExec,
+    #[error("Diagonal direction '{0}' never matches with dispatch = \"axis\"")]
+    DiagonalWithAxisDispatch(String),
+}
+
+/// The version of the config format this gestora reads. Configs without a
+/// `version` key are taken to be this version.
+pub const CONFIG_VERSION: u32 = 1;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default = "current_version")]
+    pub version: u32,
+    /// Ignore gestures while the focused output shows a fullscreen window or
+    /// an application inhibits idle, e.g. while presenting.
+    #[serde(default)]
+    pub pause_when_presenting: bool,
+    /// Ignore gestures while a window with one of these app ids is focused.
+    #[serde(default)]
+    pub inhibit_app_ids: Vec<String>,
+    /// Swipes in the same direction within this many milliseconds of each
+    /// other move one workspace further each time.
+    pub accelerate_within_ms: Option<u64>,
+    /// Swipes in the same direction within this many milliseconds of each
+    /// other count as repeats, for bindings with `repeat`. A swipe that a
+    /// binding with a higher `repeat` might still match waits this long
+    /// before it runs.
+    pub repeat_within_ms: Option<u64>,
+    /// Go back to sway's default binding mode after this many milliseconds
+    /// without gestures, once a `set-mode` action switched to another one.
+    pub mode_timeout_ms: Option<u64>,
+    /// Log how long each swipe takes from its end until sway ran its actions,
+    /// and keep a summary for `SIGUSR1`.
+    #[serde(default)]
+    pub measure_latency: bool,
+    /// How a swipe picks its binding.
+    #[serde(default)]
+    pub dispatch: Dispatch,
+    /// Shell command run by the `launcher` action.
+    pub launcher: Option<String>,
+    /// Sway command sent by the `show-desktop` action.
+    pub show_desktop: Option<String>,
+    /// Byte order of IPC headers. Only read at startup.
+    #[serde(default)]
+    pub ipc_byte_order: ByteOrder,
+    /// The magic string of IPC messages. Only read at startup.
+    #[serde(default)]
+    pub ipc_magic: Magic,
+    /// The command that switches workspaces. Only read at startup.
+    #[serde(default)]
+    pub workspace_command: WorkspaceCommand,
+    /// How gestora finds the IPC socket of the compositor. Only read at
+    /// startup.
+    #[serde(default)]
+    pub backend: Backend,
+    /// The IPC socket to connect to instead of the one the backend finds.
+    /// Only read at startup.
+    pub socket: Option<PathBuf>,
+    /// How long sway's list of workspaces is reused. Only read at startup.
+    #[serde(default = "default_workspaces_cache_ms")]
+    pub workspaces_cache_ms: u64,
+    #[serde(default)]
+    pub recognition: RecognitionConfig,
+    /// Recognition for the devices with these names, with whatever they
+    /// don't set taken from `recognition`.
+    #[serde(default, rename = "device")]
+    pub devices: BTreeMap<String, RecognitionConfig>,
+    /// The swipes learned with `gestora learn`, from [`TEMPLATES_FILE`]
+    /// next to the config.
+    #[serde(skip)]
+    pub templates: Vec<Template>,
+    /// The libinput seats to read gestures from. Only read at startup.
+    #[serde(default = "default_seats", rename = "seat")]
+    pub seats: Vec<SeatConfig>,
+    #[serde(default, rename = "output")]
+    pub outputs: Vec<OutputConfig>,
+    /// Groups of workspaces that workspace swipes stay within, with the
+    /// group actions to move between them.
+    #[serde(default, rename = "workspace_group")]
+    pub workspace_groups: Vec<WorkspaceGroup>,
+    #[serde(default = "default_bindings", rename = "binding")]
+    pub bindings: Vec<BindingConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            pause_when_presenting: false,
+            inhibit_app_ids: Vec::new(),
+            accelerate_within_ms: None,
+            repeat_within_ms: None,
+            mode_timeout_ms: None,
+            measure_latency: false,
+            dispatch: Dispatch::default(),
+            launcher: None,
+            show_desktop: None,
+            ipc_byte_order: ByteOrder::Native,
+            ipc_magic: Magic::default(),
+            workspace_command: WorkspaceCommand::default(),
+            backend: Backend::default(),
+            socket: None,
+            workspaces_cache_ms: default_workspaces_cache_ms(),
+            recognition: RecognitionConfig::default(),
+            devices: BTreeMap::new(),
+            templates: Vec::new(),
+            seats: default_seats(),
+            outputs: Vec::new(),
+            workspace_groups: Vec::new(),
+            bindings: default_bindings(),
+        }
+    }
+}
+
+/// How a swipe picks its binding from the ones matching it.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Dispatch {
+    /// The first binding for the swipe's direction wins.
+    #[default]
+    Direction,
+    /// Swipes only go along their dominant axis, north, south, east or
+    /// west, and the binding with the narrowest finger count wins, so a
+    /// binding for any number of fingers can be refined for some of them.
+    Axis,
+}
+
+/// The compositor gestora talks to.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// sway, whose socket is found with `sway --get-socketpath` unless
+    /// `socket` is set.
+    #[default]
+    Sway,
+    /// Any compositor speaking i3-ipc. Nothing is detected, so `socket` or
+    /// `--socket` has to say where it listens.
+    I3ipc,
+}
+
+/// A libinput seat and the sway its gestures go to.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SeatConfig {
+    pub name: String,
+    /// The IPC socket of the sway running the seat's actions, instead of the
+    /// one gestora connects to by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<PathBuf>,
+    /// The sockets of more sways that run the seat's actions too, e.g.
+    /// nested ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirror: Vec<PathBuf>,
+}
+
+/// The workspaces of an output that workspace swipes stay within.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    pub name: String,
+    pub first_workspace: usize,
+    pub last_workspace: usize,
+    /// Go around to the other end instead of stopping at the first and last
+    /// workspace.
+    #[serde(default)]
+    pub wrap: bool,
+}
+
+/// Workspaces that belong together, e.g. those of one project.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceGroup {
+    pub first_workspace: usize,
+    pub last_workspace: usize,
+}
+
+impl WorkspaceGroup {
+    fn contains(&self, workspace: usize) -> bool {
+        (self.first_workspace..=self.last_workspace).contains(&workspace)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorkspaceRange {
+    pub first_workspace: usize,
+    pub last_workspace: usize,
+    pub wrap: bool,
+}
+
+impl Default for WorkspaceRange {
+    fn default() -> Self {
+        WorkspaceRange {
+            first_workspace: FIRST_WORKSPACE,
+            last_workspace: LAST_WORKSPACE,
+            wrap: false,
+        }
+    }
+}
+
+impl WorkspaceRange {
+    /// The workspace `steps` away from `workspace`, forwards or backwards.
+    pub fn step(&self, workspace: usize, steps: usize, forward: bool) -> usize {
+        let (first, last) = (self.first_workspace, self.last_workspace);
+        let workspace = workspace.clamp(first, last);
+
+        if self.wrap {
+            let len = last - first + 1;
+            let offset = workspace - first;
+            let steps = steps % len;
+            let offset = if forward {
+                (offset + steps) % len
+            } else {
+                (offset + len - steps) % len
+            };
+            return first + offset;
+        }
+
+        if forward {
+            (workspace + steps).min(last)
+        } else {
+            workspace.saturating_sub(steps).max(first)
+        }
+    }
+
+    /// The lowest workspace of the range that isn't in `used`, if any.
+    pub fn first_unused(&self, used: &[usize]) -> Option<usize> {
+        (self.first_workspace..=self.last_workspace).find(|workspace| !used.contains(workspace))
+    }
+}
+
+/// A binding as written in the config file. Matchers are kept as written
+/// and only interpreted by [`Config::compile`].
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BindingConfig {
+    pub fingers: FingerSpec,
+    pub direction: DirectionSpec,
+    /// Only match swipes while this output is focused.
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Only match swipes while the focused window meets these conditions.
+    #[serde(default)]
+    pub when: Option<Condition>,
+    /// Only match swipes in this sway binding mode.
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Only match a swipe made this many times in a row, see
+    /// `repeat_within_ms`.
+    #[serde(default)]
+    pub repeat: Option<usize>,
+    pub actions: Vec<Action>,
+    /// Run instead of `actions` when one more finger rests on the touchpad
+    /// as a modifier, i.e. for the same swipe with one finger more.
+    #[serde(default)]
+    pub modified_actions: Option<Vec<Action>>,
+}
+
+/// What the focused window has to be like for a binding to match. Unset
+/// conditions always hold.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fullscreen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+}
+
+impl Condition {
+    fn holds(&self, focus: &Focus) -> bool {
+        self.fullscreen
+            .is_none_or(|fullscreen| fullscreen == focus.fullscreen)
+            && self
+                .app_id
+                .as_deref()
+                .is_none_or(|app_id| Some(app_id) == focus.app_id)
+    }
+}
+
+/// What is focused when a swipe is looked up. Anything not known is left
+/// unset.
+#[derive(Debug, Default)]
+pub struct Focus<'a> {
+    /// The binding mode, unless sway is in the default mode.
+    pub mode: Option<&'a str>,
+    pub output: Option<&'a str>,
+    pub app_id: Option<&'a str>,
+    pub fullscreen: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum FingerSpec {
+    Count(i32),
+    Pattern(String),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum DirectionSpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn version_hint(version: u32) -> &'static str {
+    if version > CONFIG_VERSION {
+        "update gestora to use it"
+    } else {
+        "remove the version key to use the current format"
+    }
+}
+
+fn current_version() -> u32 {
+    CONFIG_VERSION
+}
+
+fn default_workspaces_cache_ms() -> u64 {
+    WORKSPACES_TTL.as_millis() as u64
+}
+
+fn default_seats() -> Vec<SeatConfig> {
+    vec![SeatConfig {
+        name: "seat0".to_string(),
+        socket: None,
+        mirror: Vec::new(),
+    }]
+}
+
+fn default_bindings() -> Vec<BindingConfig> {
+    let binding = |fingers, direction: &str, action| BindingConfig {
+        fingers: FingerSpec::Count(fingers),
+        direction: DirectionSpec::One(direction.to_string()),
+        output: None,
+        when: None,
+        mode: None,
+        repeat: None,
+        actions: vec![action],
+        modified_actions: None,
+    };
+
+    vec![
+        binding(3, "west", Action::PrevWorkspace),
+        binding(3, "east", Action::NextWorkspace),
+        binding(3, "north", Action::Launcher),
+        binding(3, "south", Action::ShowDesktop),
+        binding(4, "north", Action::FocusUrgent),
+    ]
+}
+
+/// Parses a config. A `[device."name"]` table only overrides some of the
+/// recognition parameters, so it is layered over `[recognition]` first.
+/// With `dispatch = "axis"` swipes are told apart in four directions, which
+/// a `directions` of 8 anywhere contradicts.
+fn parse(contents: &str) -> Result<Config, toml::de::Error> {
+    let mut table: toml::Table = toml::from_str(contents)?;
+    let axis = table.get("dispatch").and_then(toml::Value::as_str) == Some("axis");
+    let devices = match table.get("device") {
+        Some(toml::Value::Table(devices)) => Some(devices.clone()),
+        _ => None,
+    };
+    if !axis && devices.is_none() {
+        // Parse the text itself, whose errors point at where they are.
+        return toml::from_str(contents);
+    }
+
+    let mut recognition = match table.get("recognition") {
+        Some(toml::Value::Table(recognition)) => recognition.clone(),
+        _ => toml::Table::new(),
+    };
+    if axis {
+        four_directions(&mut recognition, "[recognition]")?;
+        table.insert(
+            "recognition".to_string(),
+            toml::Value::Table(recognition.clone()),
+        );
+    }
+
+    if let Some(devices) = devices {
+        let devices = devices
+            .into_iter()
+            .map(|(name, device)| {
+                let device = match device {
+                    toml::Value::Table(mut overrides) => {
+                        if axis {
+                            four_directions(&mut overrides, &format!("[device.\"{}\"]", name))?;
+                        }
+                        let mut device = recognition.clone();
+                        device.extend(overrides);
+                        toml::Value::Table(device)
+                    }
+                    other => other,
+                };
+                Ok((name, device))
+            })
+            .collect::<Result<_, toml::de::Error>>()?;
+        table.insert("device".to_string(), toml::Value::Table(devices));
+    }
+
+    table.try_into()
+}
+
+/// Sets `directions` of the recognition in `table` to 4 for axis dispatch,
+/// or refuses another number `section` sets.
+fn four_directions(table: &mut toml::Table, section: &str) -> Result<(), toml::de::Error> {
+    match table.get("directions") {
+        None => {
+            table.insert("directions".to_string(), toml::Value::Integer(4));
+            Ok(())
+        }
+        Some(toml::Value::Integer(4)) => Ok(()),
+        Some(directions) => Err(serde::de::Error::custom(format!(
+            "directions = {} in {} can't be used with dispatch = \"axis\", which only tells swipes apart along north, south, east and west",
+            directions, section
+        ))),
+    }
+}
+
+/// The file next to the config that learned swipes are kept in.
+pub const TEMPLATES_FILE: &str = "templates.toml";
+
+/// Learned swipes as they are kept in [`TEMPLATES_FILE`].
+#[derive(Deserialize, Serialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Templates {
+    #[serde(default, rename = "template")]
+    pub templates: Vec<Template>,
+}
+
+impl Templates {
+    /// Reads the templates at `path`, none if there is no such file.
+    pub fn load(path: &Path) -> Result<Templates, ConfigError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Templates::default());
+            }
+            Err(source) => {
+                return Err(ConfigError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// Where learned swipes are kept, next to the config file that
+/// [`Config::load`] reads.
+pub fn templates_path() -> Option<PathBuf> {
+    let config = std::env::var_os("GESTORA_CONFIG")
+        .map(PathBuf::from)
+        .or_else(config_path)?;
+
+    Some(config.with_file_name(TEMPLATES_FILE))
+}
+
+/// Location of the config file, `$XDG_CONFIG_HOME/gestora/config.toml`.
+pub fn config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(base.join("gestora").join("config.toml"))
+}
+
+impl Config {
+    /// Loads the config file named by `$GESTORA_CONFIG`, or else the one at
+    /// [`config_path`], falling back to the defaults when there is none.
+    pub fn load() -> Result<Config, ConfigError> {
+        match Self::path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// The config file [`Config::load`] reads, if any.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("GESTORA_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+
+        config_path().filter(|path| path.exists())
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let parse_error = |source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        // Check the version before anything else, so a config for another
+        // version is reported as such rather than as unknown keys.
+        #[derive(Deserialize)]
+        struct Versioned {
+            #[serde(default = "current_version")]
+            version: u32,
+        }
+
+        let Versioned { version } = toml::from_str(&contents).map_err(parse_error)?;
+        if version != CONFIG_VERSION {
+            return Err(ConfigError::UnsupportedVersion {
+                path: path.to_path_buf(),
+                version,
+            });
+        }
+
+        let mut config = parse(&contents).map_err(parse_error)?;
+        config.templates = Templates::load(&path.with_file_name(TEMPLATES_FILE))?.templates;
+        Ok(config)
+    }
+
+    /// Resolves every binding's matchers once so that dispatching a swipe
+    /// never has to look at the config strings again.
+    pub fn compile(&self) -> Result<Bindings, ConfigError> {
+        if let Some(output) = self
+            .outputs
+            .iter()
+            .find(|output| output.first_workspace > output.last_workspace)
+        {
+            return Err(ConfigError::InvalidWorkspaceRange(output.name.clone()));
+        }
+
+        for (i, group) in self.workspace_groups.iter().enumerate() {
+            let overlaps = self.workspace_groups[..i].iter().any(|other| {
+                other.contains(group.first_workspace) || group.contains(other.first_workspace)
+            });
+            if group.first_workspace > group.last_workspace || overlaps {
+                return Err(ConfigError::InvalidWorkspaceGroup(
+                    group.first_workspace,
+                    group.last_workspace,
+                ));
+            }
+        }
+
+        let mut table = Vec::new();
+
+        for binding in &self.bindings {
+            let actions = self.resolve_all(&binding.actions)?;
+            let fingers = FingerMatcher::parse(&binding.fingers)?;
+            let directions = match &binding.direction {
+                DirectionSpec::One(name) => vec![parse_direction(name)?],
+                DirectionSpec::Many(names) => names
+                    .iter()
+                    .map(|name| parse_direction(name))
+                    .collect::<Result<_, _>>()?,
+            };
+            if self.dispatch == Dispatch::Axis
+                && let Some(Trigger::Swipe(dir)) = directions.iter().find(|dir| {
+                    matches!(
+                        dir,
+                        Trigger::Swipe(SwipeDir::NE | SwipeDir::NW | SwipeDir::SE | SwipeDir::SW)
+                    )
+                })
+            {
+                return Err(ConfigError::DiagonalWithAxisDispatch(
+                    dir.name().to_string(),
+                ));
+            }
+
+            let binding_for = |fingers, dir, actions: &Vec<Action>| Binding {
+                fingers,
+                dir,
+                output: binding.output.clone(),
+                when: binding.when.clone(),
+                mode: binding.mode.clone(),
+                repeat: binding.repeat,
+                actions: actions.clone(),
+            };
+
+            for &dir in &directions {
+                table.push(binding_for(fingers, dir, &actions));
+            }
+
+            let Some(modified_actions) = &binding.modified_actions else {
+                continue;
+            };
+            let FingerMatcher::Exact(count) = fingers else {
+                return Err(ConfigError::ModifierWithoutFingerCount(fingers.to_string()));
+            };
+
+            let modified_actions = self.resolve_all(modified_actions)?;
+            for &dir in &directions {
+                table.push(binding_for(
+                    FingerMatcher::Exact(count + 1),
+                    dir,
+                    &modified_actions,
+                ));
+            }
+        }
+
+        Ok(Bindings {
+            table,
+            dispatch: self.dispatch,
+        })
+    }
+
+    /// Whether any output has its own workspace range.
+    pub fn has_output_ranges(&self) -> bool {
+        !self.outputs.is_empty()
+    }
+
+    /// The group `workspace` belongs to, if any.
+    pub fn workspace_group(&self, workspace: usize) -> Option<WorkspaceGroup> {
+        self.workspace_groups
+            .iter()
+            .copied()
+            .find(|group| group.contains(workspace))
+    }
+
+    /// The first workspace of the group after or before the one `workspace`
+    /// is in, if there is one.
+    pub fn adjacent_group(&self, workspace: usize, forward: bool) -> Option<usize> {
+        let groups = self.workspace_groups.iter();
+        if forward {
+            groups
+                .filter(|group| group.first_workspace > workspace)
+                .map(|group| group.first_workspace)
+                .min()
+        } else {
+            let first = self
+                .workspace_group(workspace)
+                .map_or(workspace, |group| group.first_workspace);
+            groups
+                .filter(|group| group.last_workspace < first)
+                .max_by_key(|group| group.first_workspace)
+                .map(|group| group.first_workspace)
+        }
+    }
+
+    /// The workspaces swipes on `output` stay within.
+    pub fn workspace_range(&self, output: &str) -> WorkspaceRange {
+        self.outputs
+            .iter()
+            .find(|config| config.name == output)
+            .map(|config| WorkspaceRange {
+                first_workspace: config.first_workspace,
+                last_workspace: config.last_workspace,
+                wrap: config.wrap,
+            })
+            .unwrap_or_default()
+    }
+
+    /// The config as it is used, as TOML: defaults filled in, launcher and
+    /// show_desktop resolved and one binding per direction, in the order
+    /// they are matched.
+    pub fn effective(&self, bindings: &Bindings) -> Result<String, toml::ser::Error> {
+        #[derive(Serialize)]
+        struct Effective<'a> {
+            version: u32,
+            pause_when_presenting: bool,
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            inhibit_app_ids: &'a [String],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            accelerate_within_ms: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            repeat_within_ms: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            mode_timeout_ms: Option<u64>,
+            measure_latency: bool,
+            dispatch: Dispatch,
+            ipc_byte_order: ByteOrder,
+            ipc_magic: Magic,
+            workspace_command: &'a WorkspaceCommand,
+            backend: Backend,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            socket: Option<&'a PathBuf>,
+            workspaces_cache_ms: u64,
+            recognition: &'a RecognitionConfig,
+            #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+            device: &'a BTreeMap<String, RecognitionConfig>,
+            seat: &'a [SeatConfig],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            output: &'a [OutputConfig],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            workspace_group: &'a [WorkspaceGroup],
+            binding: Vec<EffectiveBinding<'a>>,
+        }
+
+        #[derive(Serialize)]
+        struct EffectiveBinding<'a> {
+            fingers: String,
+            direction: &'static str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            output: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            when: Option<&'a Condition>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            mode: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            repeat: Option<usize>,
+            actions: &'a [Action],
+        }
+
+        toml::to_string(&Effective {
+            version: self.version,
+            pause_when_presenting: self.pause_when_presenting,
+            inhibit_app_ids: &self.inhibit_app_ids,
+            accelerate_within_ms: self.accelerate_within_ms,
+            repeat_within_ms: self.repeat_within_ms,
+            mode_timeout_ms: self.mode_timeout_ms,
+            measure_latency: self.measure_latency,
+            dispatch: self.dispatch,
+            ipc_byte_order: self.ipc_byte_order,
+            ipc_magic: self.ipc_magic,
+            workspace_command: &self.workspace_command,
+            backend: self.backend,
+            socket: self.socket.as_ref(),
+            workspaces_cache_ms: self.workspaces_cache_ms,
+            recognition: &self.recognition,
+            device: &self.devices,
+            seat: &self.seats,
+            output: &self.outputs,
+            workspace_group: &self.workspace_groups,
+            binding: bindings
+                .table
+                .iter()
+                .map(|binding| EffectiveBinding {
+                    fingers: binding.fingers.to_string(),
+                    direction: binding.dir.name(),
+                    output: binding.output.as_deref(),
+                    when: binding.when.as_ref(),
+                    mode: binding.mode.as_deref(),
+                    repeat: binding.repeat,
+                    actions: &binding.actions,
+                })
+                .collect(),
+        })
+    }
+
+    /// Resolves `actions` and checks their placeholders.
+    fn resolve_all(&self, actions: &[Action]) -> Result<Vec<Action>, ConfigError> {
+        let actions: Vec<Action> = actions.iter().map(|action| self.resolve(action)).collect();
+
+        if let Some(name) = actions
+            .iter()
+            .find_map(|action| action.unknown_placeholder())
+        {
+            return Err(ConfigError::UnknownPlaceholder(name.to_string()));
+        }
+
+        if actions.iter().any(
+            |action| matches!(action, Action::Exec(ExecCommand::Argv(argv)) if argv.is_empty()),
+        ) {
+            return Err(ConfigError::EmptyExec);
+        }
+
+        Ok(actions)
+    }
+
+    /// Replaces actions that refer to a configured command with that command.
+    fn resolve(&self, action: &Action) -> Action {
+        match (action, &self.launcher, &self.show_desktop) {
+            (Action::Launcher, Some(launcher), _) => {
+                Action::Exec(ExecCommand::Shell(launcher.clone()))
+            }
+            (Action::ShowDesktop, _, Some(show_desktop)) => Action::Command(show_desktop.clone()),
+            _ => action.clone(),
+        }
+    }
+}
+
+/// The direction of a swipe or pinch a binding matches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    Swipe(SwipeDir),
+    Pinch(PinchDir),
+}
+
+impl Trigger {
+    fn name(&self) -> &'static str {
+        match self {
+            Trigger::Swipe(dir) => dir.name(),
+            Trigger::Pinch(dir) => dir.name(),
+        }
+    }
+}
+
+impl From<SwipeDir> for Trigger {
+    fn from(dir: SwipeDir) -> Self {
+        Trigger::Swipe(dir)
+    }
+}
+
+impl From<PinchDir> for Trigger {
+    fn from(dir: PinchDir) -> Self {
+        Trigger::Pinch(dir)
+    }
+}
+
+pub(crate) fn parse_direction(name: &str) -> Result<Trigger, ConfigError> {
+    let dir = match name {
+        "north" | "n" | "up" => SwipeDir::N,
+        "south" | "s" | "down" => SwipeDir::S,
+        "west" | "w" | "left" => SwipeDir::W,
+        "east" | "e" | "right" => SwipeDir::E,
+        "northeast" | "ne" => SwipeDir::NE,
+        "northwest" | "nw" => SwipeDir::NW,
+        "southeast" | "se" => SwipeDir::SE,
+        "southwest" | "sw" => SwipeDir::SW,
+        "pinch-in" => return Ok(Trigger::Pinch(PinchDir::In)),
+        "pinch-out" => return Ok(Trigger::Pinch(PinchDir::Out)),
+        other => return Err(ConfigError::InvalidDirection(other.to_string())),
+    };
+
+    Ok(Trigger::Swipe(dir))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FingerMatcher {
+    Any,
+    Exact(i32),
+    AtLeast(i32),
+    Range(i32, i32),
+}
+
+impl FingerMatcher {
+    fn parse(spec: &FingerSpec) -> Result<FingerMatcher, ConfigError> {
+        let pattern = match spec {
+            FingerSpec::Count(count) => return Ok(FingerMatcher::Exact(*count)),
+            FingerSpec::Pattern(pattern) => pattern.trim(),
+        };
+        let invalid = || ConfigError::InvalidFingers(pattern.to_string());
+
+        if pattern == "any" {
+            return Ok(FingerMatcher::Any);
+        }
+
+        if let Some(min) = pattern.strip_suffix('+') {
+            return min
+                .parse()
+                .map(FingerMatcher::AtLeast)
+                .map_err(|_| invalid());
+        }
+
+        if let Some((min, max)) = pattern.split_once('-') {
+            let min = min.parse().map_err(|_| invalid())?;
+            let max = max.parse().map_err(|_| invalid())?;
+            return Ok(FingerMatcher::Range(min, max));
+        }
+
+        pattern
+            .parse()
+            .map(FingerMatcher::Exact)
+            .map_err(|_| invalid())
+    }
+
+    /// How many finger counts the matcher takes, in order: one, a range,
+    /// an open end or any.
+    fn breadth(&self) -> u8 {
+        match self {
+            FingerMatcher::Exact(_) => 0,
+            FingerMatcher::Range(..) => 1,
+            FingerMatcher::AtLeast(_) => 2,
+            FingerMatcher::Any => 3,
+        }
+    }
+
+    fn matches(&self, finger_count: i32) -> bool {
+        match *self {
+            FingerMatcher::Any => true,
+            FingerMatcher::Exact(count) => finger_count == count,
+            FingerMatcher::AtLeast(min) => finger_count >= min,
+            FingerMatcher::Range(min, max) => (min..=max).contains(&finger_count),
+        }
+    }
+}
+
+impl fmt::Display for FingerMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FingerMatcher::Any => write!(f, "any"),
+            FingerMatcher::Exact(count) => write!(f, "{}", count),
+            FingerMatcher::AtLeast(min) => write!(f, "{}+", min),
+            FingerMatcher::Range(min, max) => write!(f, "{}-{}", min, max),
+        }
+    }
+}
+
+/// A binding for one direction, as compiled by [`Config::compile`].
+struct Binding {
+    fingers: FingerMatcher,
+    dir: Trigger,
+    output: Option<String>,
+    when: Option<Condition>,
+    mode: Option<String>,
+    repeat: Option<usize>,
+    actions: Vec<Action>,
+}
+
+/// The compiled binding table. The first binding matching a swipe wins, or
+/// with [`Dispatch::Axis`] the first of those with the narrowest finger
+/// count.
+pub struct Bindings {
+    table: Vec<Binding>,
+    dispatch: Dispatch,
+}
+
+impl Bindings {
+    /// The actions bound to a swipe made `repeat_count` times in a row with
+    /// `focus`. Bindings for an output or app id never match when it isn't
+    /// known, bindings without `repeat` match any number of times. A binding
+    /// mode with bindings of its own only uses those, any other mode the
+    /// bindings without one.
+    pub fn lookup(
+        &self,
+        finger_count: i32,
+        dir: impl Into<Trigger>,
+        repeat_count: usize,
+        focus: &Focus,
+    ) -> Option<&[Action]> {
+        let dir = dir.into();
+        let mode = focus.mode.filter(|&mode| {
+            self.table
+                .iter()
+                .any(|binding| binding.mode.as_deref() == Some(mode))
+        });
+        let mut matching = self.table.iter().filter(|binding| {
+            binding.mode.as_deref() == mode
+                && binding.dir == dir
+                && binding.fingers.matches(finger_count)
+                && binding.repeat.is_none_or(|repeat| repeat == repeat_count)
+                && binding
+                    .output
+                    .as_deref()
+                    .is_none_or(|name| Some(name) == focus.output)
+                && binding
+                    .when
+                    .as_ref()
+                    .is_none_or(|condition| condition.holds(focus))
+        });
+        let binding = match self.dispatch {
+            Dispatch::Direction => matching.next(),
+            Dispatch::Axis => matching.min_by_key(|binding| binding.fingers.breadth()),
+        };
+        binding.map(|binding| binding.actions.as_slice())
+    }
+
+    /// Whether a binding with a higher `repeat` than `repeat_count` could
+    /// match the swipe if it's made again, in any mode or focus.
+    pub fn repeats_further(&self, finger_count: i32, dir: SwipeDir, repeat_count: usize) -> bool {
+        let dir = Trigger::from(dir);
+        self.table.iter().any(|binding| {
+            binding.dir == dir
+                && binding.fingers.matches(finger_count)
+                && binding.repeat.is_some_and(|repeat| repeat > repeat_count)
+        })
+    }
+
+    /// Whether any binding only matches on one output, so the focused output
+    /// is needed to look up a swipe.
+    pub fn has_output_bindings(&self) -> bool {
+        self.table.iter().any(|binding| binding.output.is_some())
+    }
+
+    /// Whether any binding has conditions on the focused window, so it is
+    /// needed to look up a swipe.
+    pub fn has_conditions(&self) -> bool {
+        self.table.iter().any(|binding| binding.when.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gestora::gesture::Directions;
+
+    #[test]
+    fn device_tables_override_the_recognition() {
+        let config = parse(
+            r#"
+            [recognition]
+            gain = 1.5
+            deadzone = 20.0
+
+            [device."Magic Trackpad"]
+            gain = 3.0
+            "#,
+        )
+        .unwrap();
+
+        let trackpad = &config.devices["Magic Trackpad"];
+        assert_eq!(f64::from(trackpad.gain), 3.0);
+        assert_eq!(trackpad.deadzone, Some(20.0));
+        assert_eq!(f64::from(config.recognition.gain), 1.5);
+
+        assert!(parse("[device.touchpad]\nspeed = 2\n").is_err());
+    }
+
+    #[test]
+    fn bindings_can_target_five_fingers() {
+        let config: Config = toml::from_str(
+            r#"
+            [[binding]]
+            fingers = 5
+            direction = "east"
+            actions = ["next-workspace"]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+
+        assert!(
+            bindings
+                .lookup(5, SwipeDir::E, 1, &Focus::default())
+                .is_some()
+        );
+        assert!(
+            bindings
+                .lookup(3, SwipeDir::E, 1, &Focus::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn binding_modes_with_bindings_replace_the_default_ones() {
+        let config: Config = toml::from_str(
+            r#"
+            [[binding]]
+            mode = "resize"
+            fingers = 3
+            direction = "east"
+            actions = [{ command = "resize grow width 20px" }]
+
+            [[binding]]
+            fingers = 3
+            direction = ["east", "west"]
+            actions = ["next-workspace"]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+        let in_mode = |mode| Focus {
+            mode: Some(mode),
+            ..Focus::default()
+        };
+
+        assert!(matches!(
+            bindings.lookup(3, SwipeDir::E, 1, &in_mode("resize")),
+            Some([Action::Command(_)])
+        ));
+        assert!(
+            bindings
+                .lookup(3, SwipeDir::W, 1, &in_mode("resize"))
+                .is_none()
+        );
+        assert!(matches!(
+            bindings.lookup(3, SwipeDir::W, 1, &in_mode("passthrough")),
+            Some([Action::NextWorkspace])
+        ));
+        assert!(matches!(
+            bindings.lookup(3, SwipeDir::E, 1, &Focus::default()),
+            Some([Action::NextWorkspace])
+        ));
+    }
+
+    #[test]
+    fn output_bindings_win_on_their_output() {
+        let config: Config = toml::from_str(
+            r#"
+            [[binding]]
+            output = "eDP-1"
+            fingers = 3
+            direction = "east"
+            actions = ["last-workspace"]
+
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = ["next-workspace"]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+        let on = |output| Focus {
+            output: Some(output),
+            ..Focus::default()
+        };
+
+        assert!(matches!(
+            bindings.lookup(3, SwipeDir::E, 1, &on("eDP-1")),
+            Some([Action::LastWorkspace])
+        ));
+        assert!(matches!(
+            bindings.lookup(3, SwipeDir::E, 1, &on("HDMI-A-1")),
+            Some([Action::NextWorkspace])
+        ));
+        assert!(matches!(
+            bindings.lookup(3, SwipeDir::E, 1, &Focus::default()),
+            Some([Action::NextWorkspace])
+        ));
+    }
+
+    #[test]
+    fn conditions_check_the_focused_window() {
+        let config: Config = toml::from_str(
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "south"
+            when = { fullscreen = false, app_id = "mpv" }
+            actions = ["kill"]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+        let mpv = |fullscreen| Focus {
+            app_id: Some("mpv"),
+            fullscreen,
+            ..Focus::default()
+        };
+
+        assert!(bindings.lookup(3, SwipeDir::S, 1, &mpv(false)).is_some());
+        assert!(bindings.lookup(3, SwipeDir::S, 1, &mpv(true)).is_none());
+        assert!(
+            bindings
+                .lookup(3, SwipeDir::S, 1, &Focus::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn pinches_have_bindings_of_their_own() {
+        let config: Config = toml::from_str(
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "pinch-in"
+            actions = ["kill"]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+
+        assert!(
+            bindings
+                .lookup(3, PinchDir::In, 1, &Focus::default())
+                .is_some()
+        );
+        assert!(
+            bindings
+                .lookup(3, PinchDir::Out, 1, &Focus::default())
+                .is_none()
+        );
+        assert!(
+            bindings
+                .lookup(3, SwipeDir::S, 1, &Focus::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn exec_takes_a_shell_command_or_an_argv() {
+        let config: Config = toml::from_str(
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            actions = [{ exec = "foot -e tmux" }, { exec = ["foot", "-e", "tmux"] }]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+
+        assert!(matches!(
+            bindings.lookup(3, SwipeDir::N, 1, &Focus::default()),
+            Some([
+                Action::Exec(ExecCommand::Shell(_)),
+                Action::Exec(ExecCommand::Argv(argv)),
+            ]) if argv.len() == 3
+        ));
+
+        let empty: Config = toml::from_str(
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            actions = [{ exec = [] }]
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(empty.compile(), Err(ConfigError::EmptyExec)));
+    }
+
+    #[test]
+    fn workspace_steps_stay_in_range() {
+        let range = WorkspaceRange {
+            first_workspace: 11,
+            last_workspace: 20,
+            wrap: false,
+        };
+        assert_eq!(range.step(19, 3, true), 20);
+        assert_eq!(range.step(12, 3, false), 11);
+
+        let range = WorkspaceRange {
+            wrap: true,
+            ..range
+        };
+        assert_eq!(range.step(19, 3, true), 12);
+        assert_eq!(range.step(12, 3, false), 19);
+    }
+
+    #[test]
+    fn group_actions_jump_to_the_first_workspace_of_a_group() {
+        let config: Config = toml::from_str(
+            r#"
+            [[workspace_group]]
+            first_workspace = 1
+            last_workspace = 5
+
+            [[workspace_group]]
+            first_workspace = 6
+            last_workspace = 10
+            "#,
+        )
+        .unwrap();
+        config.compile().unwrap();
+
+        assert_eq!(config.adjacent_group(3, true), Some(6));
+        assert_eq!(config.adjacent_group(8, false), Some(1));
+        assert_eq!(config.adjacent_group(8, true), None);
+        assert_eq!(config.adjacent_group(2, false), None);
+        assert_eq!(config.adjacent_group(12, false), Some(6));
+    }
+
+    #[test]
+    fn only_scaled_commands_have_a_value() {
+        let config = |action| {
+            toml::from_str::<Config>(&format!(
+                "[[binding]]\nfingers = 3\ndirection = \"north\"\nactions = [{}]",
+                action
+            ))
+            .unwrap()
+        };
+
+        assert!(
+            config(
+                r#"{ scaled = { command = "exec brightnessctl set $value%", min = 5, max = 100 } }"#
+            )
+            .compile()
+            .is_ok()
+        );
+        assert!(matches!(
+            config(r#"{ command = "exec brightnessctl set $value%" }"#).compile(),
+            Err(ConfigError::UnknownPlaceholder(name)) if name == "value"
+        ));
+    }
+
+    #[test]
+    fn ipc_magic_has_to_be_six_bytes() {
+        let config: Config = toml::from_str(r#"ipc_magic = "swayfx""#).unwrap();
+        assert_eq!(String::from(config.ipc_magic), "swayfx");
+
+        assert!(toml::from_str::<Config>(r#"ipc_magic = "i3""#).is_err());
+    }
+
+    #[test]
+    fn workspace_command_needs_the_number() {
+        let config: Config = toml::from_str(r#"workspace_command = "workspace {}""#).unwrap();
+        assert_eq!(config.workspace_command.command(4), "workspace 4");
+        assert_eq!(
+            Config::default().workspace_command.command(4),
+            "workspace number 4"
+        );
+
+        assert!(toml::from_str::<Config>(r#"workspace_command = "workspace""#).is_err());
+    }
+
+    #[test]
+    fn overlapping_workspace_groups_are_rejected() {
+        let config: Config = toml::from_str(
+            r#"
+            [[workspace_group]]
+            first_workspace = 1
+            last_workspace = 5
+
+            [[workspace_group]]
+            first_workspace = 5
+            last_workspace = 10
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            config.compile(),
+            Err(ConfigError::InvalidWorkspaceGroup(5, 10))
+        ));
+    }
+
+    #[test]
+    fn first_unused_workspace_stays_in_range() {
+        let range = WorkspaceRange {
+            first_workspace: 1,
+            last_workspace: 4,
+            wrap: false,
+        };
+        assert_eq!(range.first_unused(&[1, 3]), Some(2));
+        assert_eq!(range.first_unused(&[1, 2, 3]), Some(4));
+        assert_eq!(range.first_unused(&[1, 2, 3, 4]), None);
+    }
+
+    #[test]
+    fn effective_config_loads_back() {
+        let config: Config = toml::from_str(
+            r#"
+            launcher = "wofi"
+
+            [[binding]]
+            fingers = "3+"
+            direction = ["north", "south"]
+            actions = ["launcher", { notify = { summary = "Hi" } }]
+            "#,
+        )
+        .unwrap();
+        let effective = config.effective(&config.compile().unwrap()).unwrap();
+
+        let reloaded: Config = toml::from_str(&effective).unwrap();
+        let bindings = reloaded.compile().unwrap();
+        assert!(matches!(
+            bindings.lookup(4, SwipeDir::S, 1, &Focus::default()),
+            Some([Action::Exec(ExecCommand::Shell(launcher)), Action::Notify { .. }])
+                if launcher == "wofi"
+        ));
+    }
+
+    #[test]
+    fn i3ipc_backend_takes_the_socket_from_the_config() {
+        let config: Config = toml::from_str(
+            r#"
+            backend = "i3ipc"
+            socket = "/run/user/1000/wayfire-ipc.sock"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.backend, Backend::I3ipc);
+        assert_eq!(
+            config.socket,
+            Some(PathBuf::from("/run/user/1000/wayfire-ipc.sock"))
+        );
+
+        assert!(toml::from_str::<Config>(r#"backend = "hyprland""#).is_err());
+    }
+
+    #[test]
+    fn repeat_bindings_only_match_repeated_swipes() {
+        let config: Config = toml::from_str(
+            r#"
+            repeat_within_ms = 400
+
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            repeat = 2
+            actions = ["launcher"]
+
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            actions = ["show-desktop"]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+        let lookup =
+            |repeat_count| bindings.lookup(3, SwipeDir::N, repeat_count, &Focus::default());
+
+        assert!(matches!(lookup(1), Some([Action::ShowDesktop])));
+        assert!(matches!(lookup(2), Some([Action::Launcher])));
+        assert!(matches!(lookup(3), Some([Action::ShowDesktop])));
+    }
+
+    #[test]
+    fn axis_dispatch_prefers_the_narrowest_finger_count() {
+        let config: Config = toml::from_str(
+            r#"
+            dispatch = "axis"
+
+            [[binding]]
+            fingers = "any"
+            direction = ["east", "west"]
+            actions = ["next-workspace"]
+
+            [[binding]]
+            fingers = "3+"
+            direction = "east"
+            actions = ["launcher"]
+
+            [[binding]]
+            fingers = 4
+            direction = "east"
+            actions = ["show-desktop"]
+            "#,
+        )
+        .unwrap();
+        let bindings = config.compile().unwrap();
+        let lookup =
+            |finger_count| bindings.lookup(finger_count, SwipeDir::E, 1, &Focus::default());
+
+        assert!(matches!(lookup(2), Some([Action::NextWorkspace])));
+        assert!(matches!(lookup(3), Some([Action::Launcher])));
+        assert!(matches!(lookup(4), Some([Action::ShowDesktop])));
+    }
+
+    #[test]
+    fn axis_dispatch_tells_swipes_apart_in_four_directions() {
+        let config = parse(
+            r#"
+            dispatch = "axis"
+
+            [device."Magic Trackpad"]
+            gain = 1.5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.recognition.directions, Directions::Four);
+        assert_eq!(
+            config.devices["Magic Trackpad"].directions,
+            Directions::Four
+        );
+    }
+
+    #[test]
+    fn axis_dispatch_rejects_eight_directions() {
+        let err = parse(
+            r#"
+            dispatch = "axis"
+
+            [device."Magic Trackpad"]
+            directions = 8
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains(r#"[device."Magic Trackpad"]"#), "{}", err);
+        assert!(err.contains(r#"dispatch = "axis""#), "{}", err);
+    }
+
+    #[test]
+    fn axis_dispatch_rejects_diagonal_bindings() {
+        let config: Config = toml::from_str(
+            r#"
+            dispatch = "axis"
+
+            [[binding]]
+            fingers = 3
+            direction = ["east", "ne"]
+            actions = ["next-workspace"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            config.compile(),
+            Err(ConfigError::DiagonalWithAxisDispatch(name)) if name == "northeast"
+        ));
+    }
+}
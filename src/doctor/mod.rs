@@ -0,0 +1,95 @@
+use crate::config::Config;
+use crate::{DeviceTracker, connect, load_config, open_seat};
+use anyhow::Context;
+use input::Event;
+use std::path::PathBuf;
+
+/// Prints whether a check passed, with what it found or why it failed.
+fn report(check: &str, result: anyhow::Result<String>) -> bool {
+    match result {
+        Ok(found) => {
+            println!("ok    {}: {}", check, found);
+            true
+        }
+        Err(err) => {
+            println!("FAIL  {}: {:#}", check, err);
+            false
+        }
+    }
+}
+
+/// Prints a check that doesn't apply, and why. It neither passes nor fails.
+fn skip(check: &str, why: &str) {
+    println!("skip  {}: {}", check, why);
+}
+
+/// How many gesture-capable devices libinput finds on the seat.
+fn count_devices(seat: &str) -> anyhow::Result<usize> {
+    let mut input = open_seat(seat)?;
+    input.dispatch().context("libinput dispatch failed")?;
+
+    let mut devices = DeviceTracker::new();
+    for event in &mut input {
+        if let Event::Device(device_event) = &event {
+            devices.handle(device_event);
+        }
+    }
+    Ok(devices.len())
+}
+
+/// Checks what gestora needs to work: that the config is valid, and that
+/// every seat has a sway to talk to and a device to read gestures from.
+/// Fails if any of them doesn't hold.
+pub fn doctor(socket: Option<&PathBuf>) -> anyhow::Result<()> {
+    let mut passed = true;
+
+    // The seats are checked with the defaults when the config is broken.
+    let config = match load_config() {
+        Ok((config, _)) => {
+            passed &= report("config", Ok("valid".to_string()));
+            config
+        }
+        Err(err) => {
+            passed &= report("config", Err(err.into()));
+            Config::default()
+        }
+    };
+
+    for seat in &config.seats {
+        let path = seat.socket.as_ref().or(socket);
+        passed &= report(
+            &format!("sway of {}", seat.name),
+            connect(path, &config)
+                .and_then(|sway| Ok(sway.get_version()?))
+                .map(|version| format!("sway {}", version.human_readable)),
+        );
+        for mirror in &seat.mirror {
+            passed &= report(
+                &format!("mirror of {}", seat.name),
+                connect(Some(mirror), &config)
+                    .and_then(|sway| Ok(sway.get_version()?))
+                    .map(|version| {
+                        format!("sway {} at {}", version.human_readable, mirror.display())
+                    }),
+            );
+        }
+
+        passed &= report(
+            &format!("devices of {}", seat.name),
+            count_devices(&seat.name).and_then(|count| match count {
+                0 => anyhow::bail!("no gesture-capable device"),
+                count => Ok(format!("{} gesture-capable", count)),
+            }),
+        );
+    }
+
+    skip(
+        "control socket writable",
+        "gestora has no control socket to check",
+    );
+
+    if !passed {
+        anyhow::bail!("Some checks failed");
+    }
+    Ok(())
+}
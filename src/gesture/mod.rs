@@ -0,0 +1,1641 @@
+use input::Device;
+use input::event::gesture::{
+    GestureEndEvent, GestureEventCoordinates, GestureEventTrait, GesturePinchEvent,
+    GesturePinchEventTrait, GestureSwipeEvent,
+};
+use input::event::{EventTrait, GestureEvent};
+use log::{debug, trace, warn};
+use rustix::time::{ClockId, clock_gettime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::f64::consts::PI;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Settings for turning finger motion into a swipe direction.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields, default)]
+pub struct RecognitionConfig {
+    /// How far the touchpad is rotated clockwise, e.g. in tablet mode.
+    pub rotation: Rotation,
+    /// Multiplies finger motion, for touchpads that report small deltas.
+    pub gain: Gain,
+    /// Flip horizontal finger motion, for touchpads that are mounted mirrored.
+    pub invert_x: bool,
+    /// Flip vertical finger motion.
+    pub invert_y: bool,
+    /// Whether swipes are told apart in four or eight directions.
+    pub directions: Directions,
+    /// What a swipe's direction is taken from.
+    pub classification: Classification,
+    /// How many updates [`Classification::Intent`] looks at.
+    pub intent_updates: usize,
+    /// Motion during this long after the fingers land is ignored, which
+    /// filters out the brief swipes a landing palm can cause.
+    pub begin_grace_ms: u64,
+    /// Reject swipes that end closer than this to where they started, whose
+    /// direction would be little more than noise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadzone: Option<f64>,
+    /// Reject swipes with fewer updates than this, which are usually one
+    /// noisy jump rather than a swipe.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_updates: Option<usize>,
+    /// Reject swipes whose [`Swipe::confidence`] is below this.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f64>,
+    /// Ignore a swipe or pinch that starts after this long without gestures,
+    /// since the first one after resuming from suspend is often garbage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_after_idle_ms: Option<u64>,
+    /// Warn about swipes whose [`Swipe::jitter`] is above this.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter_warning: Option<f64>,
+    /// Keep repeating a swipe at this interval while the fingers are held
+    /// still after it. Unset disables repeating.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_interval_ms: Option<u64>,
+    /// How long the fingers have to rest before repeating starts.
+    pub repeat_delay_ms: u64,
+    /// Report where a swipe is heading while the fingers are still down, so
+    /// its actions can be previewed and only committed when they lift.
+    pub preview: bool,
+    /// Only preview a new heading once the fingers have rested this long,
+    /// so a swipe still moving doesn't overshoot. Unset previews right away.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_delay_ms: Option<u64>,
+    /// How far the scale has to change for a pinch, e.g. 0.15 for 15%.
+    pub pinch_threshold: f64,
+}
+
+impl Default for RecognitionConfig {
+    fn default() -> Self {
+        RecognitionConfig {
+            rotation: Rotation::default(),
+            gain: Gain::default(),
+            invert_x: false,
+            invert_y: false,
+            directions: Directions::Eight,
+            classification: Classification::Displacement,
+            intent_updates: 5,
+            begin_grace_ms: 0,
+            deadzone: None,
+            min_updates: None,
+            min_confidence: None,
+            ignore_after_idle_ms: None,
+            jitter_warning: None,
+            repeat_interval_ms: None,
+            repeat_delay_ms: 400,
+            preview: false,
+            commit_delay_ms: None,
+            pinch_threshold: PINCH_COMMIT_SCALE,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid rotation {0}, expected 0, 90, 180 or 270")]
+pub struct InvalidRotation(pub u32);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(try_from = "u32", into = "u32")]
+pub struct Rotation(u32);
+
+impl Rotation {
+    pub fn degrees(&self) -> f64 {
+        self.0 as f64
+    }
+}
+
+impl From<Rotation> for u32 {
+    fn from(rotation: Rotation) -> u32 {
+        rotation.0
+    }
+}
+
+impl TryFrom<u32> for Rotation {
+    type Error = InvalidRotation;
+
+    fn try_from(degrees: u32) -> Result<Self, Self::Error> {
+        match degrees {
+            0 | 90 | 180 | 270 => Ok(Rotation(degrees)),
+            other => Err(InvalidRotation(other)),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid gain {0}, expected a positive number")]
+pub struct InvalidGain(pub f64);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Gain(f64);
+
+impl Default for Gain {
+    fn default() -> Self {
+        Gain(1.0)
+    }
+}
+
+impl From<Gain> for f64 {
+    fn from(gain: Gain) -> f64 {
+        gain.0
+    }
+}
+
+impl TryFrom<f64> for Gain {
+    type Error = InvalidGain;
+
+    fn try_from(gain: f64) -> Result<Self, Self::Error> {
+        if gain.is_finite() && gain > 0.0 {
+            Ok(Gain(gain))
+        } else {
+            Err(InvalidGain(gain))
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid number of directions {0}, expected 4 or 8")]
+pub struct InvalidDirections(pub u32);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum Directions {
+    /// Only north, south, east and west.
+    Four,
+    /// The cardinals and the diagonals between them.
+    Eight,
+}
+
+impl From<Directions> for u32 {
+    fn from(directions: Directions) -> u32 {
+        match directions {
+            Directions::Four => 4,
+            Directions::Eight => 8,
+        }
+    }
+}
+
+impl TryFrom<u32> for Directions {
+    type Error = InvalidDirections;
+
+    fn try_from(count: u32) -> Result<Self, Self::Error> {
+        match count {
+            4 => Ok(Directions::Four),
+            8 => Ok(Directions::Eight),
+            other => Err(InvalidDirections(other)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Classification {
+    /// Where the fingers ended up relative to where they started.
+    Displacement,
+    /// Where the fingers were heading during the first updates, so a swipe
+    /// that curves later still goes where the user meant it to.
+    Intent,
+    /// Where the fingers were heading over the last
+    /// [`TERMINAL_FRACTION`] of the path, so an early curve doesn't count.
+    Terminal,
+    /// The direction of the learned [`Template`] closest to the path, see
+    /// [`SwipeStateMachine::set_templates`]. Without templates it is the
+    /// same as [`Classification::Displacement`].
+    Template,
+}
+
+/// The part of a swipe's path [`Classification::Terminal`] looks at.
+pub const TERMINAL_FRACTION: f64 = 0.3;
+/// How many of the latest updates [`Classification::Terminal`] keeps.
+const RECENT_UPDATES: usize = 64;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum SwipeDir {
+    #[serde(rename = "north")]
+    N,
+    #[serde(rename = "south")]
+    S,
+    #[serde(rename = "west")]
+    W,
+    #[serde(rename = "east")]
+    E,
+    #[serde(rename = "northeast")]
+    NE,
+    #[serde(rename = "northwest")]
+    NW,
+    #[serde(rename = "southeast")]
+    SE,
+    #[serde(rename = "southwest")]
+    SW,
+}
+
+impl SwipeDir {
+    /// The name used for this direction in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SwipeDir::N => "north",
+            SwipeDir::S => "south",
+            SwipeDir::W => "west",
+            SwipeDir::E => "east",
+            SwipeDir::NE => "northeast",
+            SwipeDir::NW => "northwest",
+            SwipeDir::SE => "southeast",
+            SwipeDir::SW => "southwest",
+        }
+    }
+}
+
+/// Distance a swipe has to travel for each workspace it moves.
+pub const SWIPE_STEP_DISTANCE: f64 = 300.0;
+
+#[derive(Debug, Clone)]
+pub struct Swipe {
+    pub dir: SwipeDir,
+    pub finger_count: i32,
+    pub distance: f64,
+    /// Sway's identifier of the device the swipe came from, if known.
+    pub device: Option<String>,
+    /// Emitted while the fingers are held still after swiping, see
+    /// [`SwipeStateMachine::poll_repeat`].
+    pub held: bool,
+    /// How many swipes in the same direction came quickly before this one.
+    /// Each of them makes this swipe move one more workspace.
+    pub streak: usize,
+    /// How many times in a row this swipe was made quickly, counting itself.
+    /// 1 for a single swipe.
+    pub repeat_count: usize,
+    /// See [`SwipeVector::jitter`].
+    pub jitter: f64,
+    /// How long the fingers were down until the swipe was recognized.
+    pub duration: Duration,
+    /// When the swipe was recognized, to measure how long its actions take.
+    pub recognized_at: Instant,
+    /// libinput's time of the event the swipe began with, in microseconds,
+    /// to compare with `libinput debug-events`. Unset without libinput.
+    pub began_usec: Option<u64>,
+    /// libinput's time of the last event of the swipe.
+    pub ended_usec: Option<u64>,
+    /// How sure the direction is, from 0 to 1. It is low for a swipe whose
+    /// angle is close to another direction, or whose distance barely got
+    /// past the deadzone.
+    pub confidence: f64,
+}
+
+impl Swipe {
+    /// Number of workspaces this swipe should move, at least one. Held swipes
+    /// move one workspace per repeat.
+    pub fn steps(&self) -> usize {
+        if self.held {
+            return 1;
+        }
+
+        ((self.distance / SWIPE_STEP_DISTANCE) as usize).max(1) + self.streak
+    }
+
+    /// Average speed of the fingers, in distance per second.
+    pub fn velocity(&self) -> f64 {
+        let seconds = self.duration.as_secs_f64();
+        if seconds > 0.0 {
+            self.distance / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What the recognizer made of a gesture event.
+#[derive(Debug)]
+pub enum Recognized {
+    /// A swipe that ended, or repeats while held.
+    Swipe(Swipe),
+    /// Where a swipe is heading before the fingers lift, see
+    /// [`RecognitionConfig::preview`].
+    Preview(Swipe),
+    /// The swipe went back to where it started, or won't end in a swipe,
+    /// so the preview is to be undone.
+    CancelPreview,
+    /// A pinch that ended.
+    Pinch(Pinch),
+}
+
+/// How far a swipe has to go before it is previewed. Going back below this
+/// cancels the preview.
+pub const PREVIEW_DISTANCE: f64 = 100.0;
+
+/// Largest update that still counts as holding the fingers still.
+const HOLD_TOLERANCE: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwipeVector {
+    pub dx: f64,
+    pub dy: f64,
+    /// Sum of the squared lengths of the updates, for [`SwipeVector::jitter`].
+    sum_squares: f64,
+    updates: usize,
+}
+
+impl SwipeVector {
+    pub fn new() -> Self {
+        SwipeVector {
+            dx: 0.0,
+            dy: 0.0,
+            sum_squares: 0.0,
+            updates: 0,
+        }
+    }
+
+    /// Adds the motion of one update. Updates that aren't finite are
+    /// dropped, a buggy driver can report NaN.
+    pub fn add_update(&mut self, dx: f64, dy: f64, recognition: &RecognitionConfig) {
+        if !finite(dx, dy) {
+            return;
+        }
+
+        let (dx, dy) = (dx * recognition.gain.0, dy * recognition.gain.0);
+        self.dx += if recognition.invert_x { -dx } else { dx };
+        self.dy += if recognition.invert_y { -dy } else { dy };
+        self.sum_squares += dx * dx + dy * dy;
+        self.updates += 1;
+    }
+
+    /// How much the updates deviate from their average, i.e. the standard
+    /// deviation of the per-update motion. A steady swipe stays close to 0.
+    pub fn jitter(&self) -> f64 {
+        if self.updates == 0 {
+            return 0.0;
+        }
+
+        let n = self.updates as f64;
+        let mean_squares = (self.dx / n).powi(2) + (self.dy / n).powi(2);
+        (self.sum_squares / n - mean_squares).max(0.0).sqrt()
+    }
+
+    pub fn distance(&self) -> f64 {
+        self.dx.hypot(self.dy)
+    }
+
+    /// The angle in degrees, counter-clockwise from east.
+    fn angle(&self, recognition: &RecognitionConfig) -> f64 {
+        // libinput's y axis points down, flip it so that moving the fingers
+        // up is north.
+        let angle_rad = (-self.dy).atan2(self.dx);
+        let angle_deg = angle_rad * 180.0 / PI;
+
+        // Undo the rotation of the touchpad so directions stay relative to the user.
+        (angle_deg - recognition.rotation.degrees()).rem_euclid(360.0)
+    }
+
+    pub fn calculate_direction(&self, recognition: &RecognitionConfig) -> SwipeDir {
+        if self.dx == 0.0 && self.dy == 0.0 {
+            return SwipeDir::N; // default if no movement
+        }
+
+        classify_angle(self.angle(recognition), recognition.directions)
+    }
+
+    /// How close the angle is to the middle of its direction, from 0 on the
+    /// edge to the next direction to 1 right in the middle.
+    pub fn centeredness(&self, recognition: &RecognitionConfig) -> f64 {
+        if self.dx == 0.0 && self.dy == 0.0 {
+            return 0.0;
+        }
+
+        let sector = match recognition.directions {
+            Directions::Four => 90.0,
+            Directions::Eight => 45.0,
+        };
+        let sectors = self.angle(recognition) / sector;
+        let offset = (sectors - sectors.round()).abs() * sector;
+        1.0 - offset / (sector / 2.0)
+    }
+}
+
+/// Whether an update's motion is finite, with a warning if it isn't.
+fn finite(dx: f64, dy: f64) -> bool {
+    if dx.is_finite() && dy.is_finite() {
+        return true;
+    }
+
+    warn!("Dropping a swipe update with motion {}, {}", dx, dy);
+    false
+}
+
+/// Angles this close to a cardinal direction are treated as exactly on it.
+const CARDINAL_SNAP_DEGREES: f64 = 1.0;
+
+/// Maps an angle in degrees, counter-clockwise from east, to a direction.
+pub fn classify_angle(angle_deg: f64, directions: Directions) -> SwipeDir {
+    let cardinal = (angle_deg / 90.0).round() * 90.0;
+    let angle_deg = if (angle_deg - cardinal).abs() < CARDINAL_SNAP_DEGREES {
+        cardinal.rem_euclid(360.0)
+    } else {
+        angle_deg
+    };
+
+    if directions == Directions::Four {
+        return match angle_deg {
+            a if (45.0..135.0).contains(&a) => SwipeDir::N,
+            a if (135.0..225.0).contains(&a) => SwipeDir::W,
+            a if (225.0..315.0).contains(&a) => SwipeDir::S,
+            _ => SwipeDir::E,
+        };
+    }
+
+    match angle_deg {
+        a if (22.5..67.5).contains(&a) => SwipeDir::NE,
+        a if (67.5..112.5).contains(&a) => SwipeDir::N,
+        a if (112.5..157.5).contains(&a) => SwipeDir::NW,
+        a if (157.5..202.5).contains(&a) => SwipeDir::W,
+        a if (202.5..247.5).contains(&a) => SwipeDir::SW,
+        a if (247.5..292.5).contains(&a) => SwipeDir::S,
+        a if (292.5..337.5).contains(&a) => SwipeDir::SE,
+        _ => SwipeDir::E,
+    }
+}
+
+/// How many points a [`Template`] resamples a path to.
+pub const TEMPLATE_POINTS: usize = 16;
+
+/// The shape of a swipe that was learned for a direction, for
+/// [`Classification::Template`].
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Template {
+    #[serde(rename = "direction")]
+    pub dir: SwipeDir,
+    /// [`TEMPLATE_POINTS`] points evenly spaced along the path, relative to
+    /// where it started and scaled to a path of length 1.
+    pub points: Vec<[f64; 2]>,
+}
+
+impl Template {
+    /// Learns the path of finger positions as a swipe in `dir`. Fails for a
+    /// path that doesn't go anywhere.
+    pub fn learn(dir: SwipeDir, path: &[(f64, f64)]) -> Option<Template> {
+        Some(Template {
+            dir,
+            points: Template::shape(path)?,
+        })
+    }
+
+    /// Resamples a path of finger positions the way templates are stored,
+    /// so paths of any speed and size compare.
+    pub fn shape(path: &[(f64, f64)]) -> Option<Vec<[f64; 2]>> {
+        let segment = |(ax, ay): (f64, f64), (bx, by): (f64, f64)| (bx - ax).hypot(by - ay);
+        let length: f64 = path.windows(2).map(|pair| segment(pair[0], pair[1])).sum();
+        if length == 0.0 {
+            return None;
+        }
+
+        let (x0, y0) = path[0];
+        let point = |x: f64, y: f64| [(x - x0) / length, (y - y0) / length];
+        let step = length / (TEMPLATE_POINTS - 1) as f64;
+
+        let mut points = vec![point(x0, y0)];
+        let mut walked = 0.0;
+        for pair in path.windows(2) {
+            let ((ax, ay), (bx, by)) = (pair[0], pair[1]);
+            let length = segment(pair[0], pair[1]);
+            while points.len() < TEMPLATE_POINTS && points.len() as f64 * step <= walked + length {
+                let t = (points.len() as f64 * step - walked) / length;
+                points.push(point(ax + t * (bx - ax), ay + t * (by - ay)));
+            }
+            walked += length;
+        }
+
+        // Rounding can leave the end of the path out.
+        let (x, y) = path[path.len() - 1];
+        points.resize(TEMPLATE_POINTS, point(x, y));
+        Some(points)
+    }
+
+    /// The average distance between the template and a shape from
+    /// [`Template::shape`].
+    pub fn distance(&self, shape: &[[f64; 2]]) -> f64 {
+        let sum: f64 = self
+            .points
+            .iter()
+            .zip(shape)
+            .map(|(a, b)| (a[0] - b[0]).hypot(a[1] - b[1]))
+            .sum();
+        sum / self.points.len().max(1) as f64
+    }
+}
+
+/// The template closest to `shape`, if there are any.
+pub fn nearest_template<'a>(templates: &'a [Template], shape: &[[f64; 2]]) -> Option<&'a Template> {
+    templates
+        .iter()
+        .min_by(|a, b| a.distance(shape).total_cmp(&b.distance(shape)))
+}
+
+pub struct SwipeStateMachine {
+    recognition: RecognitionConfig,
+    /// The templates [`Classification::Template`] picks from.
+    templates: Arc<[Template]>,
+    finger_count: i32,
+    accumulated_swipe: SwipeVector,
+    /// The motion of the first [`RecognitionConfig::intent_updates`] updates.
+    initial_swipe: SwipeVector,
+    updates: usize,
+    /// The latest updates, kept for [`Classification::Terminal`].
+    recent: VecDeque<(f64, f64)>,
+    /// The length of the whole path the fingers took.
+    path_length: f64,
+    /// Where the fingers were after each update, from where they started.
+    path: Vec<(f64, f64)>,
+    began: Instant,
+    /// When the fingers last moved more than [`HOLD_TOLERANCE`].
+    last_motion: Instant,
+    next_repeat: Option<Instant>,
+    repeated: bool,
+    /// The direction and steps last previewed, unless the preview was
+    /// cancelled.
+    previewed: Option<(SwipeDir, usize)>,
+    /// Whether this swipe was previewed at all.
+    was_previewed: bool,
+}
+
+impl SwipeStateMachine {
+    pub fn new(recognition: RecognitionConfig) -> Self {
+        SwipeStateMachine {
+            recognition,
+            templates: Vec::new().into(),
+            finger_count: 0,
+            accumulated_swipe: SwipeVector::new(),
+            initial_swipe: SwipeVector::new(),
+            updates: 0,
+            recent: VecDeque::with_capacity(RECENT_UPDATES),
+            path_length: 0.0,
+            path: vec![(0.0, 0.0)],
+            began: Instant::now(),
+            last_motion: Instant::now(),
+            next_repeat: None,
+            repeated: false,
+            previewed: None,
+            was_previewed: false,
+        }
+    }
+
+    /// Starts a fresh swipe. libinput can drop the end of a gesture, so
+    /// nothing of a previous swipe is carried over.
+    pub fn begin(&mut self, finger_count: i32) {
+        *self = SwipeStateMachine {
+            finger_count,
+            templates: Arc::clone(&self.templates),
+            ..SwipeStateMachine::new(self.recognition)
+        };
+    }
+
+    pub fn set_templates(&mut self, templates: Vec<Template>) {
+        self.templates = templates.into();
+    }
+
+    /// Where the fingers went during the current or last swipe, for
+    /// [`Template::learn`].
+    pub fn path(&self) -> &[(f64, f64)] {
+        &self.path
+    }
+
+    pub fn update(&mut self, dx: f64, dy: f64) {
+        if self.began.elapsed() < Duration::from_millis(self.recognition.begin_grace_ms)
+            || !finite(dx, dy)
+        {
+            return;
+        }
+
+        self.accumulated_swipe.add_update(dx, dy, &self.recognition);
+        self.path
+            .push((self.accumulated_swipe.dx, self.accumulated_swipe.dy));
+        if self.updates < self.recognition.intent_updates {
+            self.initial_swipe.add_update(dx, dy, &self.recognition);
+            self.updates += 1;
+        }
+
+        if self.recognition.classification == Classification::Terminal {
+            if self.recent.len() == RECENT_UPDATES {
+                self.recent.pop_front();
+            }
+            self.recent.push_back((dx, dy));
+            self.path_length += dx.hypot(dy);
+        }
+
+        // Moving again stops repeating until the fingers settle down.
+        if dx.hypot(dy) > HOLD_TOLERANCE {
+            self.last_motion = Instant::now();
+            self.next_repeat = None;
+        }
+    }
+
+    /// The motion over the last [`TERMINAL_FRACTION`] of the path, or as
+    /// much of it as the recent updates cover.
+    fn terminal_swipe(&self) -> SwipeVector {
+        let mut terminal = SwipeVector::new();
+        let mut length = 0.0;
+
+        for &(dx, dy) in self.recent.iter().rev() {
+            if length >= self.path_length * TERMINAL_FRACTION {
+                break;
+            }
+            terminal.add_update(dx, dy, &self.recognition);
+            length += dx.hypot(dy);
+        }
+
+        terminal
+    }
+
+    fn swipe(&self, held: bool) -> Swipe {
+        let terminal;
+        let motion = match self.recognition.classification {
+            Classification::Intent if self.initial_swipe.distance() > 0.0 => &self.initial_swipe,
+            Classification::Terminal if self.path_length > 0.0 => {
+                terminal = self.terminal_swipe();
+                &terminal
+            }
+            _ => &self.accumulated_swipe,
+        };
+        let learned = match self.recognition.classification {
+            Classification::Template => Template::shape(&self.path)
+                .and_then(|shape| nearest_template(&self.templates, &shape))
+                .map(|template| template.dir),
+            _ => None,
+        };
+
+        // Full confidence in the distance once it is twice the deadzone.
+        let distance = self.accumulated_swipe.distance();
+        let threshold = self.recognition.deadzone.unwrap_or(SWIPE_COMMIT_DISTANCE);
+        let margin = ((distance - threshold) / threshold).clamp(0.0, 1.0);
+
+        Swipe {
+            finger_count: self.finger_count,
+            dir: learned.unwrap_or_else(|| motion.calculate_direction(&self.recognition)),
+            distance,
+            device: None,
+            held,
+            streak: 0,
+            repeat_count: 1,
+            jitter: self.accumulated_swipe.jitter(),
+            duration: self.began.elapsed(),
+            recognized_at: Instant::now(),
+            began_usec: None,
+            ended_usec: None,
+            confidence: motion.centeredness(&self.recognition) * margin,
+        }
+    }
+
+    /// When [`SwipeStateMachine::poll_repeat`] will next emit a swipe, if
+    /// the fingers stay where they are.
+    pub fn repeat_deadline(&self) -> Option<Instant> {
+        self.recognition.repeat_interval_ms?;
+
+        if self.finger_count == 0 || self.accumulated_swipe.distance() < SWIPE_STEP_DISTANCE {
+            return None;
+        }
+
+        Some(
+            self.next_repeat.unwrap_or(
+                self.last_motion + Duration::from_millis(self.recognition.repeat_delay_ms),
+            ),
+        )
+    }
+
+    /// Emits a held swipe once the fingers have rested after swiping far
+    /// enough, and then again every repeat interval until they lift.
+    pub fn poll_repeat(&mut self, now: Instant) -> Option<Swipe> {
+        let interval = self.recognition.repeat_interval_ms?;
+
+        if now < self.repeat_deadline()? {
+            return None;
+        }
+
+        self.next_repeat = Some(now + Duration::from_millis(interval));
+        self.repeated = true;
+        Some(self.swipe(true))
+    }
+
+    /// Reports where the swipe is heading whenever that changes, if
+    /// previews are on.
+    pub fn preview(&mut self, now: Instant) -> Option<Recognized> {
+        if !self.recognition.preview {
+            return None;
+        }
+
+        if self.accumulated_swipe.distance() < PREVIEW_DISTANCE {
+            return self.previewed.take().map(|_| Recognized::CancelPreview);
+        }
+
+        let swipe = self.swipe(false);
+        let heading = (swipe.dir, swipe.steps());
+        if self.previewed == Some(heading) {
+            return None;
+        }
+
+        if let Some(delay) = self.recognition.commit_delay_ms
+            && now < self.last_motion + Duration::from_millis(delay)
+        {
+            return None;
+        }
+
+        self.previewed = Some(heading);
+        self.was_previewed = true;
+        Some(Recognized::Preview(swipe))
+    }
+
+    /// When [`SwipeStateMachine::preview`] will preview a new heading that
+    /// waits for the commit delay, if the fingers stay where they are.
+    pub fn preview_deadline(&self) -> Option<Instant> {
+        let delay = self.recognition.commit_delay_ms?;
+
+        if !self.recognition.preview
+            || self.finger_count == 0
+            || self.accumulated_swipe.distance() < PREVIEW_DISTANCE
+        {
+            return None;
+        }
+
+        let swipe = self.swipe(false);
+        if self.previewed == Some((swipe.dir, swipe.steps())) {
+            return None;
+        }
+
+        Some(self.last_motion + Duration::from_millis(delay))
+    }
+
+    /// Whether a preview is showing that ending the swipe has to commit or
+    /// cancel.
+    pub fn previewing(&self) -> bool {
+        self.previewed.is_some()
+    }
+
+    pub fn end(&mut self) -> Result<Swipe, Rejection> {
+        let distance = self.accumulated_swipe.distance();
+        if self.was_previewed && self.previewed.is_none() {
+            self.finger_count = 0;
+            return Err(Rejection::PreviewCancelled { distance });
+        }
+
+        if let Some(radius) = self.recognition.deadzone
+            && distance < radius
+        {
+            self.finger_count = 0;
+            return Err(Rejection::InDeadzone { distance, radius });
+        }
+
+        let updates = self.accumulated_swipe.updates;
+        if let Some(min) = self.recognition.min_updates
+            && updates < min
+        {
+            self.finger_count = 0;
+            return Err(Rejection::TooFewUpdates { updates, min });
+        }
+
+        let swipe = self.swipe(false);
+        self.finger_count = 0;
+
+        if let Some(min) = self.recognition.min_confidence
+            && swipe.confidence < min
+        {
+            return Err(Rejection::LowConfidence {
+                confidence: swipe.confidence,
+                min,
+            });
+        }
+
+        if let Some(limit) = self.recognition.jitter_warning
+            && swipe.jitter > limit
+        {
+            warn!(
+                "Swipe jitter {:.1} is above {:.1}, the touchpad may need palm rejection or have a firmware problem",
+                swipe.jitter, limit
+            );
+        }
+
+        // Holding already ran the actions, lifting the fingers just stops it.
+        if self.repeated {
+            return Err(Rejection::Held {
+                distance: swipe.distance,
+            });
+        }
+
+        Ok(swipe)
+    }
+}
+
+/// Why a swipe ended without running anything.
+#[derive(Error, Debug, Clone, Copy, PartialEq)]
+pub enum Rejection {
+    #[error("held until it repeated, distance {distance:.0}")]
+    Held { distance: f64 },
+    #[error("distance {distance:.0} is within the deadzone of {radius:.0}")]
+    InDeadzone { distance: f64, radius: f64 },
+    #[error("only {updates} updates, fewer than {min}")]
+    TooFewUpdates { updates: usize, min: usize },
+    #[error("confidence {confidence:.2} is below {min:.2}")]
+    LowConfidence { confidence: f64, min: f64 },
+    #[error("went back before lifting, distance {distance:.0}")]
+    PreviewCancelled { distance: f64 },
+    #[error("part of a pinch, distance {distance:.0}")]
+    PartOfPinch { distance: f64 },
+    #[error("first gesture after {idle_ms} ms without gestures")]
+    AfterIdle { idle_ms: u64 },
+}
+
+/// Distance after which a gesture counts as a swipe rather than a pinch.
+const SWIPE_COMMIT_DISTANCE: f64 = 30.0;
+/// Change in scale after which a gesture counts as a pinch rather than a
+/// swipe, unless `pinch_threshold` says otherwise.
+const PINCH_COMMIT_SCALE: f64 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PinchDir {
+    In,
+    Out,
+}
+
+impl PinchDir {
+    /// The name used for this direction in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PinchDir::In => "pinch-in",
+            PinchDir::Out => "pinch-out",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pinch {
+    pub dir: PinchDir,
+    pub finger_count: i32,
+    pub scale: f64,
+    /// Sway's identifier of the device the pinch came from, if known.
+    pub device: Option<String>,
+}
+
+pub struct PinchStateMachine {
+    finger_count: i32,
+    scale: f64,
+    /// How far the scale has to change for [`PinchStateMachine::end`] to
+    /// report a pinch.
+    threshold: f64,
+}
+
+impl Default for PinchStateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PinchStateMachine {
+    pub fn new() -> Self {
+        PinchStateMachine {
+            finger_count: 0,
+            scale: 1.0,
+            threshold: PINCH_COMMIT_SCALE,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: f64) {
+        self.threshold = threshold;
+    }
+
+    pub fn begin(&mut self, finger_count: i32) {
+        self.finger_count = finger_count;
+        self.scale = 1.0;
+    }
+
+    /// libinput reports the scale relative to the start of the pinch.
+    pub fn update(&mut self, scale: f64) {
+        self.scale = scale;
+    }
+
+    pub fn scale_delta(&self) -> f64 {
+        (self.scale - 1.0).abs()
+    }
+
+    /// Whether the scale changed enough to count as a pinch.
+    pub fn past_threshold(&self) -> bool {
+        self.scale_delta() >= self.threshold
+    }
+
+    pub fn end(&mut self) -> Option<Pinch> {
+        let finger_count = self.finger_count;
+        self.finger_count = 0;
+
+        if !self.past_threshold() {
+            return None;
+        }
+
+        let dir = if self.scale < 1.0 {
+            PinchDir::In
+        } else {
+            PinchDir::Out
+        };
+
+        Some(Pinch {
+            dir,
+            finger_count,
+            scale: self.scale,
+            device: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GestureKind {
+    Swipe,
+    Pinch,
+}
+
+/// Recognizes swipes and pinches. libinput may cancel a gesture and carry on
+/// with the other kind while the fingers stay down, so once a physical gesture
+/// has moved enough to count as one kind, the other one is ignored until the
+/// fingers lift. That way a zoom never also switches workspaces.
+pub struct GestureRecognizer {
+    swipe: SwipeStateMachine,
+    pinch: PinchStateMachine,
+    committed: Option<GestureKind>,
+    recognition: RecognitionConfig,
+    /// Recognition for the devices with these names instead of
+    /// `recognition`.
+    devices: BTreeMap<String, RecognitionConfig>,
+    /// Sway's identifier of the device the current swipe comes from.
+    device: Option<String>,
+    /// libinput's times of the first and latest event of the current swipe.
+    began_usec: Option<u64>,
+    ended_usec: Option<u64>,
+    /// When the last gesture event arrived, in microseconds since boot.
+    last_event_usec: Option<u64>,
+    /// How long it was idle before the current swipe or pinch, which is
+    /// thrown away.
+    ignoring: Option<u64>,
+}
+
+impl GestureRecognizer {
+    pub fn new(recognition: RecognitionConfig) -> Self {
+        GestureRecognizer {
+            swipe: SwipeStateMachine::new(recognition),
+            pinch: {
+                let mut pinch = PinchStateMachine::new();
+                pinch.set_threshold(recognition.pinch_threshold);
+                pinch
+            },
+            committed: None,
+            recognition,
+            devices: BTreeMap::new(),
+            device: None,
+            began_usec: None,
+            ended_usec: None,
+            last_event_usec: None,
+            ignoring: None,
+        }
+    }
+
+    fn commit(&mut self, kind: GestureKind) {
+        if self.committed.is_none() {
+            debug!("Committing gesture to {:?}", kind);
+            self.committed = Some(kind);
+        }
+    }
+
+    /// Whether a gesture of `kind` that just ended should produce output.
+    /// A cancelled gesture only counts if it was the one committed to.
+    fn finish(&mut self, kind: GestureKind, cancelled: bool) -> bool {
+        let emit = match self.committed {
+            Some(committed) => committed == kind,
+            None => !cancelled,
+        };
+
+        // A cancelled gesture means libinput carries on with another one
+        // while the fingers are still down.
+        if !cancelled {
+            self.committed = None;
+        }
+
+        emit
+    }
+
+    /// Feeds a swipe event to the state machine. Returns the swipe once it
+    /// ends or why it was rejected, and previews in between.
+    fn handle_swipe_gesture(
+        &mut self,
+        gesture: &GestureSwipeEvent,
+    ) -> Option<Result<Recognized, Rejection>> {
+        match gesture {
+            GestureSwipeEvent::Begin(begin) => {
+                self.swipe.begin(begin.finger_count());
+                None
+            }
+            GestureSwipeEvent::Update(update) => {
+                self.swipe.update(update.dx(), update.dy());
+                if self.swipe.accumulated_swipe.distance() >= SWIPE_COMMIT_DISTANCE {
+                    self.commit(GestureKind::Swipe);
+                }
+                if self.committed == Some(GestureKind::Pinch) {
+                    return None;
+                }
+                self.swipe.preview(Instant::now()).map(Ok)
+            }
+            GestureSwipeEvent::End(end) => {
+                let distance = self.swipe.accumulated_swipe.distance();
+                let previewing = self.swipe.previewing();
+                let mut result = self.swipe.end();
+                if !self.finish(GestureKind::Swipe, end.cancelled()) {
+                    result = Err(Rejection::PartOfPinch { distance });
+                }
+
+                match result {
+                    Ok(swipe) => Some(Ok(Recognized::Swipe(swipe))),
+                    Err(rejection) if previewing => {
+                        debug!("Rejected swipe: {}", rejection);
+                        Some(Ok(Recognized::CancelPreview))
+                    }
+                    Err(rejection) => Some(Err(rejection)),
+                }
+            }
+            other => {
+                debug!("Ignoring unhandled swipe event {:?}", other);
+                None
+            }
+        }
+    }
+
+    /// Feeds a pinch event to the state machine. Returns the pinch once it
+    /// ends.
+    fn handle_pinch_gesture(&mut self, gesture: &GesturePinchEvent) -> Option<Pinch> {
+        match gesture {
+            GesturePinchEvent::Begin(begin) => {
+                self.pinch.begin(begin.finger_count());
+                None
+            }
+            GesturePinchEvent::Update(update) => {
+                self.pinch.update(update.scale());
+                if self.pinch.past_threshold() {
+                    self.commit(GestureKind::Pinch);
+                }
+                None
+            }
+            GesturePinchEvent::End(end) => {
+                let pinch = self.pinch.end();
+                if !self.finish(GestureKind::Pinch, end.cancelled()) {
+                    debug!("Ignoring pinch that is part of a swipe");
+                    return None;
+                }
+
+                let pinch = pinch?;
+                debug!(
+                    "Recognized {} finger pinch {:?}, scale {:.2}",
+                    pinch.finger_count, pinch.dir, pinch.scale
+                );
+                Some(pinch)
+            }
+            other => {
+                debug!("Ignoring unhandled pinch event {:?}", other);
+                None
+            }
+        }
+    }
+
+    pub fn set_templates(&mut self, templates: Vec<Template>) {
+        self.swipe.set_templates(templates);
+    }
+
+    pub fn set_recognition(&mut self, recognition: RecognitionConfig) {
+        self.recognition = recognition;
+        self.use_recognition(recognition);
+    }
+
+    /// Recognizes the gestures of the devices with these names with their
+    /// own settings, from their next gesture on.
+    pub fn set_device_recognition(&mut self, devices: BTreeMap<String, RecognitionConfig>) {
+        self.devices = devices;
+    }
+
+    fn use_recognition(&mut self, recognition: RecognitionConfig) {
+        self.pinch.set_threshold(recognition.pinch_threshold);
+        self.swipe.recognition = recognition;
+    }
+
+    /// Switches to the settings of the device a gesture begins on.
+    fn begin_on(&mut self, device: &Device) {
+        let recognition = self
+            .devices
+            .get(device.name())
+            .copied()
+            .unwrap_or(self.recognition);
+        self.use_recognition(recognition);
+        self.device = Some(device_identifier(device));
+    }
+
+    /// Whether fingers are down on a swipe or pinch.
+    pub fn in_gesture(&self) -> bool {
+        self.swipe.finger_count != 0 || self.pinch.finger_count != 0
+    }
+
+    /// When the main loop has to call [`GestureRecognizer::poll_repeat`] next.
+    pub fn repeat_deadline(&self) -> Option<Instant> {
+        if self.committed == Some(GestureKind::Pinch) || self.ignoring.is_some() {
+            return None;
+        }
+
+        self.swipe.repeat_deadline()
+    }
+
+    pub fn poll_repeat(&mut self, now: Instant) -> Option<Swipe> {
+        if self.committed == Some(GestureKind::Pinch) || self.ignoring.is_some() {
+            return None;
+        }
+
+        let mut swipe = self.swipe.poll_repeat(now)?;
+        self.stamp(&mut swipe);
+        Some(swipe)
+    }
+
+    /// When the main loop has to call [`GestureRecognizer::poll_preview`]
+    /// next.
+    pub fn preview_deadline(&self) -> Option<Instant> {
+        if self.committed == Some(GestureKind::Pinch) || self.ignoring.is_some() {
+            return None;
+        }
+
+        self.swipe.preview_deadline()
+    }
+
+    /// Previews a swipe whose fingers rested for the commit delay since the
+    /// last update.
+    pub fn poll_preview(&mut self, now: Instant) -> Option<Recognized> {
+        if now < self.preview_deadline()? {
+            return None;
+        }
+
+        let mut recognized = self.swipe.preview(now)?;
+        if let Recognized::Preview(swipe) = &mut recognized {
+            self.stamp(swipe);
+        }
+        Some(recognized)
+    }
+
+    /// Tells a swipe where and when it happened.
+    fn stamp(&self, swipe: &mut Swipe) {
+        swipe.device = self.device.clone();
+        swipe.began_usec = self.began_usec;
+        swipe.ended_usec = self.ended_usec;
+    }
+
+    /// How long there were no gestures, if that is longer than the
+    /// configured idle gap. libinput's event times are on the monotonic
+    /// clock, which stands still while suspended, so this uses the boot clock
+    /// instead.
+    fn after_idle(&mut self) -> Option<u64> {
+        let now = clock_gettime(ClockId::Boottime);
+        let time_usec = now.tv_sec as u64 * 1_000_000 + now.tv_nsec as u64 / 1000;
+        let last = self.last_event_usec.replace(time_usec)?;
+        let idle_ms = time_usec.saturating_sub(last) / 1000;
+
+        (idle_ms >= self.swipe.recognition.ignore_after_idle_ms?).then_some(idle_ms)
+    }
+
+    pub fn handle(&mut self, gesture: &GestureEvent) -> Option<Recognized> {
+        let after_idle = self.after_idle();
+        log_event(gesture);
+
+        match gesture {
+            GestureEvent::Swipe(swipe) => {
+                if let GestureSwipeEvent::Begin(_) = swipe {
+                    self.begin_on(&gesture.device());
+                    self.ignoring = after_idle;
+                    self.began_usec = Some(gesture.time_usec());
+                }
+                self.ended_usec = Some(gesture.time_usec());
+
+                let ended = matches!(swipe, GestureSwipeEvent::End(_));
+                let result = self.handle_swipe_gesture(swipe)?;
+                if let Some(idle_ms) = self.ignoring {
+                    if ended {
+                        self.ignoring = None;
+                        debug!("Rejected swipe: {}", Rejection::AfterIdle { idle_ms });
+                    }
+                    return None;
+                }
+
+                match result {
+                    Ok(mut recognized) => {
+                        if let Recognized::Swipe(swipe) | Recognized::Preview(swipe) =
+                            &mut recognized
+                        {
+                            self.stamp(swipe);
+                        }
+                        Some(recognized)
+                    }
+                    Err(rejection) => {
+                        debug!("Rejected swipe: {}", rejection);
+                        None
+                    }
+                }
+            }
+            GestureEvent::Pinch(pinch) => {
+                if let GesturePinchEvent::Begin(_) = pinch {
+                    self.begin_on(&gesture.device());
+                    self.ignoring = after_idle;
+                }
+
+                let ended = matches!(pinch, GesturePinchEvent::End(_));
+                let pinch = self.handle_pinch_gesture(pinch);
+                if let Some(idle_ms) = self.ignoring {
+                    if ended {
+                        self.ignoring = None;
+                        debug!("Rejected pinch: {}", Rejection::AfterIdle { idle_ms });
+                    }
+                    return None;
+                }
+
+                let mut pinch = pinch?;
+                pinch.device = self.device.clone();
+                Some(Recognized::Pinch(pinch))
+            }
+            other => {
+                debug!("Ignoring unhandled gesture event {:?}", other);
+                None
+            }
+        }
+    }
+}
+
+/// Logs a gesture event with libinput's time, to line it up with
+/// `libinput debug-events`. Updates are only traced, there are many of them.
+fn log_event(gesture: &GestureEvent) {
+    let time_usec = gesture.time_usec();
+    let (kind, phase) = match gesture {
+        GestureEvent::Swipe(GestureSwipeEvent::Begin(_)) => ("swipe", "begin"),
+        GestureEvent::Swipe(GestureSwipeEvent::Update(_)) => ("swipe", "update"),
+        GestureEvent::Swipe(GestureSwipeEvent::End(_)) => ("swipe", "end"),
+        GestureEvent::Pinch(GesturePinchEvent::Begin(_)) => ("pinch", "begin"),
+        GestureEvent::Pinch(GesturePinchEvent::Update(_)) => ("pinch", "update"),
+        GestureEvent::Pinch(GesturePinchEvent::End(_)) => ("pinch", "end"),
+        _ => return,
+    };
+
+    if phase == "update" {
+        trace!(time_usec; "{} {} at {} us", kind, phase, time_usec);
+    } else {
+        debug!(time_usec; "{} {} at {} us", kind, phase, time_usec);
+    }
+}
+
+/// The identifier sway uses for `device`, so we can find the seat it is on.
+fn device_identifier(device: &Device) -> String {
+    let name: String = device
+        .name()
+        .trim()
+        .chars()
+        .map(|c| if c == ' ' || c.is_control() { '_' } else { c })
+        .collect();
+
+    format!("{}:{}:{}", device.id_vendor(), device.id_product(), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn five_finger_begin_propagates_to_swipe() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig::default());
+        state_machine.begin(5);
+        state_machine.update(400.0, 0.0);
+
+        let swipe = state_machine.end().unwrap();
+        assert_eq!(swipe.finger_count, 5);
+        assert_eq!(swipe.dir, SwipeDir::E);
+    }
+
+    #[test]
+    fn intent_classification_ignores_later_curves() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            classification: Classification::Intent,
+            intent_updates: 2,
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        state_machine.update(20.0, 0.0);
+        state_machine.update(20.0, 0.0);
+        state_machine.update(0.0, -300.0);
+
+        assert_eq!(state_machine.end().unwrap().dir, SwipeDir::E);
+    }
+
+    #[test]
+    fn terminal_classification_ignores_early_curves() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            classification: Classification::Terminal,
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        for _ in 0..6 {
+            state_machine.update(0.0, -20.0);
+        }
+        for _ in 0..5 {
+            state_machine.update(20.0, 0.0);
+        }
+
+        assert_eq!(state_machine.end().unwrap().dir, SwipeDir::E);
+    }
+
+    #[test]
+    fn held_swipe_is_rejected_when_the_fingers_lift() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            repeat_interval_ms: Some(100),
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        state_machine.update(400.0, 0.0);
+
+        let later = Instant::now() + Duration::from_secs(1);
+        assert!(state_machine.poll_repeat(later).is_some());
+        assert_eq!(
+            state_machine.end().unwrap_err(),
+            Rejection::Held { distance: 400.0 }
+        );
+    }
+
+    #[test]
+    fn swipe_lasts_from_begin_to_end() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig::default());
+        state_machine.begin(3);
+        state_machine.update(200.0, 0.0);
+        std::thread::sleep(Duration::from_millis(20));
+
+        let swipe = state_machine.end().unwrap();
+        assert!(swipe.duration >= Duration::from_millis(20));
+        assert!(swipe.velocity() <= 200.0 / 0.02);
+
+        // The next swipe starts its own clock.
+        state_machine.begin(3);
+        state_machine.update(200.0, 0.0);
+        assert!(state_machine.end().unwrap().duration < swipe.duration);
+    }
+
+    #[test]
+    fn begin_without_end_starts_clean() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig::default());
+        state_machine.begin(3);
+        state_machine.update(400.0, 0.0);
+
+        // The end of the first swipe never arrives.
+        state_machine.begin(4);
+        state_machine.update(0.0, -200.0);
+
+        let swipe = state_machine.end().unwrap();
+        assert_eq!(swipe.dir, SwipeDir::N);
+        assert_eq!(swipe.finger_count, 4);
+        assert_eq!(swipe.distance, 200.0);
+    }
+
+    #[test]
+    fn short_swipe_is_rejected_in_the_deadzone() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            deadzone: Some(20.0),
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        state_machine.update(3.0, 4.0);
+
+        assert_eq!(
+            state_machine.end().unwrap_err(),
+            Rejection::InDeadzone {
+                distance: 5.0,
+                radius: 20.0
+            }
+        );
+    }
+
+    #[test]
+    fn single_jump_has_too_few_updates() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            min_updates: Some(3),
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        state_machine.update(400.0, 0.0);
+
+        assert_eq!(
+            state_machine.end().unwrap_err(),
+            Rejection::TooFewUpdates { updates: 1, min: 3 }
+        );
+
+        state_machine.begin(3);
+        for _ in 0..3 {
+            state_machine.update(100.0, 0.0);
+        }
+        assert!(state_machine.end().is_ok());
+    }
+
+    #[test]
+    fn confidence_drops_near_another_direction() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            min_confidence: Some(0.5),
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        state_machine.update(300.0, 0.0);
+        assert_eq!(state_machine.end().unwrap().confidence, 1.0);
+
+        // Almost halfway between east and north-east.
+        state_machine.begin(3);
+        state_machine.update(300.0, -120.0);
+        assert!(matches!(
+            state_machine.end().unwrap_err(),
+            Rejection::LowConfidence { confidence, .. } if confidence < 0.1
+        ));
+
+        // Right on east, but only just past the deadzone.
+        state_machine.begin(3);
+        state_machine.update(40.0, 0.0);
+        assert!(matches!(
+            state_machine.end().unwrap_err(),
+            Rejection::LowConfidence { confidence, .. } if (confidence - 1.0 / 3.0).abs() < 1e-9
+        ));
+    }
+
+    #[test]
+    fn templates_classify_by_the_shape_of_the_path() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            classification: Classification::Template,
+            ..RecognitionConfig::default()
+        });
+        // North is learned as a hook that starts out east.
+        let east = Template::learn(SwipeDir::E, &[(0.0, 0.0), (300.0, 0.0)]).unwrap();
+        let hook = [(0.0, 0.0), (100.0, 0.0), (100.0, -100.0)];
+        let north = Template::learn(SwipeDir::N, &hook).unwrap();
+        state_machine.set_templates(vec![east, north]);
+
+        state_machine.begin(3);
+        for _ in 0..10 {
+            state_machine.update(20.0, 0.0);
+        }
+        for _ in 0..10 {
+            state_machine.update(0.0, -20.0);
+        }
+        assert_eq!(state_machine.end().unwrap().dir, SwipeDir::N);
+
+        state_machine.begin(3);
+        state_machine.update(100.0, 0.0);
+        state_machine.update(100.0, 1.0);
+        assert_eq!(state_machine.end().unwrap().dir, SwipeDir::E);
+    }
+
+    #[test]
+    fn swiping_back_cancels_the_preview() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            preview: true,
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        state_machine.update(150.0, 0.0);
+        assert!(matches!(
+            state_machine.preview(Instant::now()),
+            Some(Recognized::Preview(Swipe {
+                dir: SwipeDir::E,
+                ..
+            }))
+        ));
+        state_machine.update(10.0, 0.0);
+        assert!(state_machine.preview(Instant::now()).is_none());
+
+        state_machine.update(-150.0, 0.0);
+        assert!(matches!(
+            state_machine.preview(Instant::now()),
+            Some(Recognized::CancelPreview)
+        ));
+        assert!(matches!(
+            state_machine.end(),
+            Err(Rejection::PreviewCancelled { .. })
+        ));
+    }
+
+    #[test]
+    fn commit_delay_waits_for_the_fingers_to_rest() {
+        let mut state_machine = SwipeStateMachine::new(RecognitionConfig {
+            preview: true,
+            commit_delay_ms: Some(200),
+            ..RecognitionConfig::default()
+        });
+        state_machine.begin(3);
+        state_machine.update(150.0, 0.0);
+        assert!(state_machine.preview(Instant::now()).is_none());
+
+        let deadline = state_machine.preview_deadline().unwrap();
+        assert!(matches!(
+            state_machine.preview(deadline),
+            Some(Recognized::Preview(Swipe {
+                dir: SwipeDir::E,
+                ..
+            }))
+        ));
+        assert!(state_machine.preview_deadline().is_none());
+    }
+
+    #[test]
+    fn steady_swipe_has_no_jitter() {
+        let recognition = RecognitionConfig::default();
+        let mut steady = SwipeVector::new();
+        let mut shaky = SwipeVector::new();
+        for i in 0..10 {
+            steady.add_update(5.0, 0.0, &recognition);
+            shaky.add_update(5.0, if i % 2 == 0 { 4.0 } else { -4.0 }, &recognition);
+        }
+
+        assert!(steady.jitter() < 1e-9);
+        assert!((shaky.jitter() - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn angles_next_to_cardinals_snap_to_them() {
+        assert_eq!(classify_angle(179.9, Directions::Eight), SwipeDir::W);
+        assert_eq!(classify_angle(180.1, Directions::Eight), SwipeDir::W);
+        assert_eq!(classify_angle(359.9, Directions::Eight), SwipeDir::E);
+        assert_eq!(classify_angle(0.1, Directions::Eight), SwipeDir::E);
+        assert_eq!(classify_angle(90.5, Directions::Eight), SwipeDir::N);
+    }
+
+    #[test]
+    fn diagonal_depends_on_direction_count() {
+        assert_eq!(classify_angle(45.0, Directions::Eight), SwipeDir::NE);
+        assert_eq!(classify_angle(45.0, Directions::Four), SwipeDir::N);
+        assert_eq!(classify_angle(44.9, Directions::Four), SwipeDir::E);
+        assert_eq!(classify_angle(225.0, Directions::Four), SwipeDir::S);
+    }
+
+    #[test]
+    fn non_finite_updates_are_dropped() {
+        let recognition = RecognitionConfig::default();
+        let mut swipe = SwipeVector::new();
+        swipe.add_update(300.0, 0.0, &recognition);
+        swipe.add_update(f64::NAN, 10.0, &recognition);
+        swipe.add_update(0.0, f64::INFINITY, &recognition);
+
+        assert_eq!((swipe.dx, swipe.dy), (300.0, 0.0));
+        assert_eq!(swipe.calculate_direction(&recognition), SwipeDir::E);
+
+        let mut state_machine = SwipeStateMachine::new(recognition);
+        state_machine.begin(3);
+        state_machine.update(f64::NAN, f64::NAN);
+        state_machine.update(0.0, -300.0);
+        let swipe = state_machine.end().unwrap();
+        assert_eq!((swipe.dir, swipe.distance), (SwipeDir::N, 300.0));
+    }
+
+    #[test]
+    fn moving_fingers_up_is_north() {
+        let recognition = RecognitionConfig::default();
+        let mut swipe = SwipeVector::new();
+        swipe.add_update(0.0, -300.0, &recognition);
+        assert_eq!(swipe.calculate_direction(&recognition), SwipeDir::N);
+    }
+
+    #[test]
+    fn nearly_horizontal_swipe_is_cardinal() {
+        let recognition = RecognitionConfig::default();
+        let mut swipe = SwipeVector::new();
+        swipe.add_update(-300.0, 0.2, &recognition);
+        assert_eq!(swipe.calculate_direction(&recognition), SwipeDir::W);
+
+        let mut swipe = SwipeVector::new();
+        swipe.add_update(-300.0, -0.2, &recognition);
+        assert_eq!(swipe.calculate_direction(&recognition), SwipeDir::W);
+    }
+
+    #[test]
+    fn inverted_axes_mirror_the_swipe() {
+        let recognition = RecognitionConfig {
+            invert_x: true,
+            ..RecognitionConfig::default()
+        };
+        let mut swipe = SwipeVector::new();
+        swipe.add_update(300.0, -300.0, &recognition);
+        assert_eq!(swipe.calculate_direction(&recognition), SwipeDir::NW);
+    }
+
+    #[test]
+    fn pinches_need_the_configured_threshold() {
+        let mut pinch = PinchStateMachine::new();
+        pinch.set_threshold(0.3);
+        pinch.begin(3);
+        pinch.update(0.8);
+        assert!(!pinch.past_threshold());
+        assert!(pinch.end().is_none());
+
+        pinch.begin(3);
+        pinch.update(0.6);
+        assert!(pinch.past_threshold());
+        assert!(matches!(
+            pinch.end(),
+            Some(Pinch {
+                dir: PinchDir::In,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+//! Logging to journald over its native protocol, so the key-values of a log
+//! record, such as `direction` and `fingers`, become fields of their own in
+//! `journalctl -o json`.
+
+use log::kv::{Error, Key, Value, VisitSource};
+use log::{Level, Log, Metadata, Record};
+use std::fs::File;
+use std::io;
+use std::os::fd::AsFd;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Whether systemd connected stderr to the journal, i.e. gestora runs as a
+/// service. `JOURNAL_STREAM` holds the device and inode of that stream.
+fn connected() -> bool {
+    let Some(stream) = std::env::var_os("JOURNAL_STREAM") else {
+        return false;
+    };
+    let Ok(stderr) = io::stderr().as_fd().try_clone_to_owned() else {
+        return false;
+    };
+    let Ok(metadata) = File::from(stderr).metadata() else {
+        return false;
+    };
+
+    stream.to_str() == Some(&format!("{}:{}", metadata.dev(), metadata.ino()))
+}
+
+/// Sends log records to journald. Records are filtered like env_logger
+/// filters them.
+pub(crate) struct JournalLogger {
+    socket: UnixDatagram,
+    filter: env_logger::Logger,
+}
+
+fn open(socket: &Path) -> io::Result<UnixDatagram> {
+    let datagram = UnixDatagram::unbound()?;
+    datagram.connect(socket)?;
+    Ok(datagram)
+}
+
+/// The journal's logger on `socket`, if there is one to use, or else
+/// `filter` logging to stderr, with why the socket didn't open.
+fn choose(
+    socket: Option<io::Result<UnixDatagram>>,
+    filter: env_logger::Logger,
+) -> (Box<dyn Log>, Option<io::Error>) {
+    match socket {
+        Some(Ok(socket)) => (Box::new(JournalLogger { socket, filter }), None),
+        Some(Err(err)) => (Box::new(filter), Some(err)),
+        None => (Box::new(filter), None),
+    }
+}
+
+/// Installs a logger with the max level of `filter`. Under systemd it logs
+/// to the journal, and falls back to stderr if it isn't there after all.
+pub(crate) fn init(filter: env_logger::Logger) -> Result<(), log::SetLoggerError> {
+    let max_level = filter.filter();
+    let socket = connected().then(|| open(Path::new(JOURNAL_SOCKET)));
+    let (logger, err) = choose(socket, filter);
+    log::set_boxed_logger(logger)?;
+    log::set_max_level(max_level);
+    if let Some(err) = err {
+        log::warn!("Can't log to the journal: {}", err);
+    }
+    Ok(())
+}
+
+/// Appends `NAME=value` in the journal's native format, which needs a length
+/// prefix for values that contain a newline.
+fn append_field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+/// Turns a key into a journal field name, which only has uppercase letters,
+/// digits and underscores and can't start with an underscore.
+fn field_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.trim_start_matches(|c: char| c == '_' || c.is_ascii_digit())
+        .to_string()
+}
+
+struct Fields<'a>(&'a mut Vec<u8>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        let name = field_name(key.as_str());
+        if !name.is_empty() {
+            append_field(self.0, &name, &value.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// syslog's priority for `level`.
+fn priority(level: Level) -> &'static str {
+    match level {
+        Level::Error => "3",
+        Level::Warn => "4",
+        Level::Info => "6",
+        Level::Debug | Level::Trace => "7",
+    }
+}
+
+impl Log for JournalLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+
+        let mut entry = Vec::new();
+        append_field(&mut entry, "MESSAGE", &record.args().to_string());
+        append_field(&mut entry, "PRIORITY", priority(record.level()));
+        append_field(&mut entry, "SYSLOG_IDENTIFIER", "gestora");
+        append_field(&mut entry, "TARGET", record.target());
+        let _ = record.key_values().visit(&mut Fields(&mut entry));
+
+        // There is nowhere left to report a failure to log.
+        let _ = self.socket.send(&entry);
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_stderr_without_a_journal_socket() {
+        let path = std::env::temp_dir().join(format!("gestora-journal-{}", std::process::id()));
+        let filter = env_logger::Builder::new()
+            .filter_level(log::LevelFilter::Info)
+            .build();
+
+        let (logger, err) = choose(Some(open(&path)), filter);
+
+        assert!(err.is_some());
+        assert!(logger.enabled(&Metadata::builder().level(Level::Info).build()));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long swipes took from being recognized until sway ran their actions.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Summary {
+    count: u32,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl Summary {
+    pub fn record(&mut self, latency: Duration) {
+        if self.count == 0 || latency < self.min {
+            self.min = latency;
+        }
+        self.max = self.max.max(latency);
+        self.total += latency;
+        self.count += 1;
+    }
+
+    /// The summary of the swipes of both.
+    pub fn merge(self, other: Summary) -> Summary {
+        match (self.count, other.count) {
+            (0, _) => other,
+            (_, 0) => self,
+            _ => Summary {
+                count: self.count + other.count,
+                total: self.total + other.total,
+                min: self.min.min(other.min),
+                max: self.max.max(other.max),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.count == 0 {
+            return write!(f, "no swipes measured");
+        }
+
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{} swipes, min {:.1} ms, avg {:.1} ms, max {:.1} ms",
+            self.count,
+            ms(self.min),
+            ms(self.total / self.count),
+            ms(self.max)
+        )
+    }
+}
+
+/// A [`Summary`] shared between the worker measuring swipes and the main
+/// loop reporting it.
+#[derive(Clone, Default)]
+pub(crate) struct Latency(Arc<Mutex<Summary>>);
+
+impl Latency {
+    pub fn record(&self, latency: Duration) {
+        self.0.lock().unwrap().record(latency);
+    }
+
+    pub fn summary(&self) -> Summary {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_keeps_the_extremes_and_the_average() {
+        let mut first = Summary::default();
+        first.record(Duration::from_millis(4));
+        first.record(Duration::from_millis(2));
+        let mut second = Summary::default();
+        second.record(Duration::from_millis(9));
+
+        assert_eq!(
+            first.merge(Summary::default()).merge(second).to_string(),
+            "3 swipes, min 2.0 ms, avg 5.0 ms, max 9.0 ms"
+        );
+        assert_eq!(Summary::default().to_string(), "no swipes measured");
+    }
+}
@@ -0,0 +1,4 @@
+pub mod gesture;
+pub mod sink;
+#[cfg(feature = "sway")]
+pub mod sway;
@@ -1,20 +1,39 @@
-mod sway;
+mod action;
+mod audit;
+mod config;
+mod doctor;
+mod journal;
+mod latency;
+mod mode;
+mod replay;
+#[cfg(feature = "watch")]
+mod watch;
+mod worker;
 
-use sway::Sway;
+use anyhow::Context;
+use config::{Backend, Bindings, Config};
+use gestora::gesture::{GestureRecognizer, Recognized, Swipe, SwipeDir};
+use gestora::sink::GestureSink;
+use gestora::sway::Sway;
+use log::{error, info, warn};
 
-use input::event::gesture::{
-    GestureEventCoordinates, GestureEventTrait, GestureSwipeBeginEvent, GestureSwipeUpdateEvent,
-};
-use std::f64::consts::PI;
-
-use input::event::GestureEvent;
-use input::event::gesture::GestureSwipeEvent;
-use input::{Event, Libinput, LibinputInterface};
-use libc::{O_RDONLY, O_RDWR, O_WRONLY};
-use rustix::event::{PollFd, PollFlags, poll};
+use audit::AuditLog;
+use input::event::{DeviceEvent, EventTrait};
+use input::{DeviceCapability, Event, Libinput, LibinputInterface};
+use libc::{O_ACCMODE, O_RDONLY, O_RDWR, O_WRONLY};
+use mode::Mode;
+use rustix::event::{PollFd, PollFlags, Timespec, poll};
+use std::collections::HashSet;
 use std::fs::{File, OpenOptions};
 use std::os::unix::{fs::OpenOptionsExt, io::OwnedFd};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use worker::{Fanout, SwaySink};
+
+const FIRST_WORKSPACE: usize = 1;
+const LAST_WORKSPACE: usize = 10;
 
 struct Interface;
 
@@ -22,8 +41,8 @@ impl LibinputInterface for Interface {
     fn open_restricted(&mut self, path: &Path, flags: i32) -> Result<OwnedFd, i32> {
         OpenOptions::new()
             .custom_flags(flags)
-            .read((flags & O_RDONLY != 0) | (flags & O_RDWR != 0))
-            .write((flags & O_WRONLY != 0) | (flags & O_RDWR != 0))
+            .read((flags & O_ACCMODE == O_RDONLY) | (flags & O_ACCMODE == O_RDWR))
+            .write((flags & O_ACCMODE == O_WRONLY) | (flags & O_ACCMODE == O_RDWR))
             .open(path)
             .map(|file| file.into())
             .map_err(|err| err.raw_os_error().unwrap())
@@ -33,171 +52,598 @@ impl LibinputInterface for Interface {
     }
 }
 
-enum SwipeDir {
-    N,
-    S,
-    W,
-    E,
-    NE,
-    NW,
-    SE,
-    SW,
+/// Counts swipes in the same direction that quickly follow each other, so
+/// that swiping repeatedly moves further each time, and so that bindings can
+/// match a swipe repeated a number of times.
+struct Streak {
+    within: Option<Duration>,
+    repeat_within: Option<Duration>,
+    last: Option<(SwipeDir, i32, Instant)>,
+    count: usize,
+    repeats: usize,
 }
 
-struct Swipe {
-    dir: SwipeDir,
-    finger_count: i32,
+impl Streak {
+    fn new(config: &Config) -> Self {
+        Streak {
+            within: config.accelerate_within_ms.map(Duration::from_millis),
+            repeat_within: config.repeat_within_ms.map(Duration::from_millis),
+            last: None,
+            count: 0,
+            repeats: 1,
+        }
+    }
+
+    fn track(&mut self, swipe: &mut Swipe) {
+        let now = Instant::now();
+        let since = match self.last {
+            Some((dir, finger_count, at))
+                if dir == swipe.dir && finger_count == swipe.finger_count =>
+            {
+                Some(now.duration_since(at))
+            }
+            _ => None,
+        };
+        let follows = |within: Option<Duration>| {
+            since
+                .zip(within)
+                .is_some_and(|(since, within)| since <= within)
+        };
+
+        self.count = if follows(self.within) {
+            self.count + 1
+        } else {
+            0
+        };
+        self.repeats = if follows(self.repeat_within) {
+            self.repeats + 1
+        } else {
+            1
+        };
+        self.last = Some((swipe.dir, swipe.finger_count, now));
+        swipe.streak = self.count;
+        swipe.repeat_count = self.repeats;
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct SwipeVector {
-    dx: f64,
-    dy: f64,
+fn handle(
+    event: &Event,
+    recognizer: &mut GestureRecognizer,
+    streak: &mut Streak,
+    sinks: &mut [&mut dyn GestureSink],
+) {
+    let input::Event::Gesture(gesture) = event else {
+        return;
+    };
+
+    dispatch(recognizer.handle(gesture), streak, sinks);
 }
 
-impl SwipeVector {
-    fn new() -> Self {
-        SwipeVector { dx: 0.0, dy: 0.0 }
+fn dispatch(
+    recognized: Option<Recognized>,
+    streak: &mut Streak,
+    sinks: &mut [&mut dyn GestureSink],
+) {
+    match recognized {
+        Some(Recognized::Swipe(mut swipe)) => {
+            streak.track(&mut swipe);
+            for sink in sinks {
+                sink.handle(&swipe);
+            }
+        }
+        Some(Recognized::Preview(swipe)) => {
+            for sink in sinks {
+                sink.preview(&swipe);
+            }
+        }
+        Some(Recognized::CancelPreview) => {
+            for sink in sinks {
+                sink.cancel_preview();
+            }
+        }
+        Some(Recognized::Pinch(pinch)) => {
+            for sink in sinks {
+                sink.handle_pinch(&pinch);
+            }
+        }
+        None => {}
     }
+}
 
-    fn add_update(&mut self, update: &GestureSwipeUpdateEvent) {
-        self.dx += update.dx();
-        self.dy += update.dy();
+/// Watches the config file, if there is one and inotify works.
+#[cfg(feature = "watch")]
+fn config_watcher() -> Option<watch::ConfigWatcher> {
+    let path = Config::path()?;
+    match watch::ConfigWatcher::new(&path) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            warn!("Not watching {}: {}", path.display(), err);
+            None
+        }
     }
+}
+
+#[cfg(feature = "dbus")]
+fn dbus_sink() -> anyhow::Result<Box<dyn GestureSink>> {
+    Ok(Box::new(gestora::sink::DbusSink::connect()?))
+}
+
+#[cfg(not(feature = "dbus"))]
+fn dbus_sink() -> anyhow::Result<Box<dyn GestureSink>> {
+    anyhow::bail!("--dbus needs gestora to be built with the dbus feature")
+}
+
+/// Keeps track of the gesture-capable devices on the seat, so we notice when
+/// the last one goes away and another one shows up.
+struct DeviceTracker {
+    devices: HashSet<String>,
+    lost_all: bool,
+}
 
-    fn calculate_direction(&self) -> SwipeDir {
-        if self.dx == 0.0 && self.dy == 0.0 {
-            return SwipeDir::N; // default if no movement
+impl DeviceTracker {
+    fn new() -> Self {
+        DeviceTracker {
+            devices: HashSet::new(),
+            lost_all: false,
         }
+    }
 
-        let angle_rad = self.dy.atan2(self.dx);
-        let angle_deg = angle_rad * 180.0 / PI;
+    fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
 
-        let angle_deg = if angle_deg < 0.0 {
-            angle_deg + 360.0
-        } else {
-            angle_deg
-        };
+    fn len(&self) -> usize {
+        self.devices.len()
+    }
 
-        match angle_deg {
-            a if (22.5..67.5).contains(&a) => SwipeDir::NE,
-            a if (67.5..112.5).contains(&a) => SwipeDir::N,
-            a if (112.5..157.5).contains(&a) => SwipeDir::NW,
-            a if (157.5..202.5).contains(&a) => SwipeDir::W,
-            a if (202.5..247.5).contains(&a) => SwipeDir::SW,
-            a if (247.5..292.5).contains(&a) => SwipeDir::S,
-            a if (292.5..337.5).contains(&a) => SwipeDir::SE,
-            _ => SwipeDir::E,
+    /// Returns true when a gesture device appeared after all of them were gone.
+    fn handle(&mut self, event: &DeviceEvent) -> bool {
+        match event {
+            DeviceEvent::Added(added) => {
+                let device = added.device();
+                info!("Device added: {} ({})", device.name(), device.sysname());
+
+                if !device.has_capability(DeviceCapability::Gesture) {
+                    return false;
+                }
+
+                self.devices.insert(device.sysname().to_string());
+                std::mem::take(&mut self.lost_all)
+            }
+            DeviceEvent::Removed(removed) => {
+                let device = removed.device();
+                info!("Device removed: {} ({})", device.name(), device.sysname());
+
+                if self.devices.remove(device.sysname()) && self.devices.is_empty() {
+                    warn!("No gesture-capable devices left on the seat");
+                    self.lost_all = true;
+                }
+
+                false
+            }
+            _ => false,
         }
     }
 }
 
-struct SwipeStateMachine {
-    finger_count: i32,
-    accumulated_swipe: SwipeVector,
+fn open_seat(name: &str) -> anyhow::Result<Libinput> {
+    let mut input = Libinput::new_with_udev(Interface);
+    input
+        .udev_assign_seat(name)
+        .map_err(|_| anyhow::anyhow!("Failed to assign {}", name))?;
+
+    Ok(input)
 }
 
-impl SwipeStateMachine {
-    fn new() -> Self {
-        SwipeStateMachine {
-            finger_count: 0,
-            accumulated_swipe: SwipeVector::new(),
+/// Connects to the sway at `path`, or else the config's `socket`, before
+/// falling back to what the backend detects.
+fn connect(path: Option<&PathBuf>, config: &Config) -> anyhow::Result<Sway> {
+    let mut sway = match (path.or(config.socket.as_ref()), config.backend) {
+        (Some(path), _) => Sway::connect_to(path)?,
+        (None, Backend::Sway) => Sway::new()?,
+        (None, Backend::I3ipc) => {
+            anyhow::bail!("backend = \"i3ipc\" needs a socket, either in the config or --socket")
+        }
+    };
+    sway.set_byte_order(config.ipc_byte_order);
+    sway.set_magic(config.ipc_magic);
+    sway.set_workspace_command(config.workspace_command.clone());
+    sway.set_workspaces_ttl(Duration::from_millis(config.workspaces_cache_ms));
+    Ok(sway)
+}
+
+fn recognizer(config: &Config) -> GestureRecognizer {
+    let mut recognizer = GestureRecognizer::new(config.recognition);
+    recognizer.set_device_recognition(config.devices.clone());
+    recognizer.set_templates(config.templates.clone());
+    recognizer
+}
+
+/// A libinput seat, the state of the gestures on it and the sways its
+/// actions go to.
+struct Seat {
+    name: String,
+    input: Libinput,
+    recognizer: GestureRecognizer,
+    streak: Streak,
+    devices: DeviceTracker,
+    sink: Fanout,
+}
+
+impl Seat {
+    fn open(
+        name: &str,
+        sways: Vec<Sway>,
+        config: &Arc<Config>,
+        bindings: &Arc<Bindings>,
+    ) -> anyhow::Result<Seat> {
+        let sinks = sways
+            .into_iter()
+            .map(|sway| {
+                let mode = Mode::follow(&sway).unwrap_or_else(|err| {
+                    warn!("Can't follow sway's binding mode: {}", err);
+                    Mode::default()
+                });
+                SwaySink::new(sway, Arc::clone(config), Arc::clone(bindings), mode)
+            })
+            .collect();
+        let mut seat = Seat {
+            name: name.to_string(),
+            input: open_seat(name)?,
+            recognizer: recognizer(config),
+            streak: Streak::new(config),
+            devices: DeviceTracker::new(),
+            sink: Fanout::new(sinks),
+        };
+
+        // libinput announces the devices already on the seat on the first
+        // dispatch.
+        seat.input.dispatch().context("libinput dispatch failed")?;
+        for event in &mut seat.input {
+            if let Event::Device(device_event) = &event {
+                seat.devices.handle(device_event);
+            }
         }
+
+        Ok(seat)
     }
 
-    fn begin(&mut self, begin: &GestureSwipeBeginEvent) {
-        self.finger_count = begin.finger_count();
-        self.accumulated_swipe = SwipeVector::new();
+    fn set_config(&mut self, config: &Arc<Config>, bindings: &Arc<Bindings>) {
+        self.sink.set_config(config, bindings);
+        self.recognizer.set_recognition(config.recognition);
+        self.recognizer
+            .set_device_recognition(config.devices.clone());
+        self.recognizer.set_templates(config.templates.clone());
+        self.streak = Streak::new(config);
     }
 
-    fn update(&mut self, update: &GestureSwipeUpdateEvent) {
-        self.accumulated_swipe.add_update(&update);
+    /// When the main loop has to wake up for a held or resting swipe.
+    fn deadline(&self) -> Option<Instant> {
+        [
+            self.recognizer.repeat_deadline(),
+            self.recognizer.preview_deadline(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
     }
 
-    fn end(&mut self) -> Option<Swipe> {
-        let dir = self.accumulated_swipe.calculate_direction();
-        let finger_count = self.finger_count;
-        self.finger_count = 0;
+    /// Handles the events that arrived on the seat and the swipes that are
+    /// due, passing gestures on to the seat's sway and the shared sinks.
+    fn dispatch(
+        &mut self,
+        config: &Config,
+        extra_sinks: &mut Vec<Box<dyn GestureSink>>,
+    ) -> anyhow::Result<()> {
+        if let Err(err) = self.input.dispatch() {
+            // A closed fd won't come back, anything else may be a hiccup of
+            // one device.
+            if err.raw_os_error() == Some(libc::EBADF) {
+                return Err(anyhow::Error::new(err).context("libinput dispatch failed"));
+            }
+            warn!("libinput dispatch failed on {}: {}", self.name, err);
+        }
 
-        Some(Swipe { finger_count, dir })
-    }
-}
+        let mut restart = false;
+        for event in &mut self.input {
+            if let Event::Device(device_event) = &event {
+                restart |= self.devices.handle(device_event);
+            }
+            handle(
+                &event,
+                &mut self.recognizer,
+                &mut self.streak,
+                &mut [&mut self.sink, extra_sinks],
+            );
+        }
 
-fn handle_swipe_gesture(
-    gesture: &GestureSwipeEvent,
-    state_machine: &mut SwipeStateMachine,
-) -> Option<Swipe> {
-    match gesture {
-        GestureSwipeEvent::Begin(begin) => {
-            state_machine.begin(begin);
-            None
+        if let Some(swipe) = self.recognizer.poll_repeat(Instant::now()) {
+            self.sink.handle(&swipe);
+            extra_sinks.handle(&swipe);
         }
-        GestureSwipeEvent::Update(update) => {
-            state_machine.update(update);
-            None
+        dispatch(
+            self.recognizer.poll_preview(Instant::now()),
+            &mut self.streak,
+            &mut [&mut self.sink, extra_sinks],
+        );
+
+        // A context that lost every gesture device does not always pick up
+        // gestures from a replugged one, so start over with a fresh context.
+        if restart {
+            info!(
+                "Gesture device is back, restarting the libinput context of {}",
+                self.name
+            );
+            self.input = open_seat(&self.name)?;
+            self.recognizer = recognizer(config);
+            self.devices = DeviceTracker::new();
         }
-        GestureSwipeEvent::End(_) => state_machine.end(),
-        _ => None,
+
+        Ok(())
     }
 }
 
-fn handle_gesture(gesture: &GestureEvent, state_machine: &mut SwipeStateMachine) -> Option<Swipe> {
-    match gesture {
-        GestureEvent::Swipe(swipe) => handle_swipe_gesture(swipe, state_machine),
-        _ => None,
-    }
+fn load_config() -> Result<(Config, Bindings), config::ConfigError> {
+    let config = Config::load()?;
+    let bindings = config.compile()?;
+
+    Ok((config, bindings))
 }
 
-fn handle(
-    event: &Event,
-    sway: &mut Sway,
-    state_machine: &mut SwipeStateMachine,
-) -> anyhow::Result<()> {
-    match event {
-        input::Event::Gesture(gesture) => {
-            if let Some(swipe) = handle_gesture(gesture, state_machine) {
-                let direction = swipe.dir;
-                let finger_count = swipe.finger_count;
-
-                let active_workspace = sway.get_active_workspace()?;
-
-                if finger_count == 3 {
-                    match direction {
-                        SwipeDir::W => {
-                            if active_workspace - 1 > 0 {
-                                sway.set_active_workspace(active_workspace - 1)?;
-                            }
-                        }
-                        SwipeDir::E => {
-                            if active_workspace + 1 <= 10 {
-                                sway.set_active_workspace(active_workspace + 1)?;
-                            }
-                        }
-                        _ => {}
-                    }
-                }
+struct Args {
+    socket: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    learn: Option<(String, PathBuf)>,
+    doctor: bool,
+    print_config: bool,
+    dbus: bool,
+    audit_log: Option<PathBuf>,
+    require_device: bool,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut args = Args {
+        socket: None,
+        replay: None,
+        learn: None,
+        doctor: false,
+        print_config: false,
+        dbus: false,
+        audit_log: None,
+        require_device: false,
+    };
+    let mut iter = std::env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "replay" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("replay requires a recording"))?;
+                args.replay = Some(PathBuf::from(path));
             }
+            "learn" => {
+                let (Some(direction), Some(path)) = (iter.next(), iter.next()) else {
+                    anyhow::bail!("learn requires a direction and a recording");
+                };
+                args.learn = Some((direction, PathBuf::from(path)));
+            }
+            "doctor" => args.doctor = true,
+            "--print-config" => args.print_config = true,
+            "--dbus" => args.dbus = true,
+            "--require-device" => args.require_device = true,
+            "--audit-log" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--audit-log requires a path"))?;
+                args.audit_log = Some(PathBuf::from(path));
+            }
+            "--socket" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--socket requires a path"))?;
+                args.socket = Some(PathBuf::from(path));
+            }
+            other => anyhow::bail!("Unknown argument: {}", other),
         }
-        _ => {}
     }
 
-    Ok(())
+    Ok(args)
 }
 
 fn main() -> Result<(), anyhow::Error> {
-    let mut sway = Sway::new()?;
+    // Without RUST_LOG, gestora's debug messages pass the logger and the max
+    // level filters them instead, so the toggle-debug-log action can turn
+    // them on at runtime.
+    let custom_filter = std::env::var_os("RUST_LOG").is_some();
+    let mut logger = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("info,gestora=debug"),
+    );
+    // Under systemd, log to the journal with structured fields.
+    journal::init(logger.build())?;
+    if !custom_filter {
+        log::set_max_level(log::LevelFilter::Info);
+    }
 
-    let mut input = Libinput::new_with_udev(Interface);
-    let mut state_machine = SwipeStateMachine::new();
+    let args = parse_args()?;
+
+    if let Some(recording) = &args.replay {
+        return replay::replay(recording);
+    }
+
+    if let Some((direction, recording)) = &args.learn {
+        return replay::learn(direction, recording);
+    }
+
+    if args.doctor {
+        return doctor::doctor(args.socket.as_ref());
+    }
+
+    if args.print_config {
+        let (config, bindings) = load_config()?;
+        print!("{}", config.effective(&bindings)?);
+        return Ok(());
+    }
+
+    let (config, bindings) = load_config()?;
+    let mut config = Arc::new(config);
+    let bindings = Arc::new(bindings);
+
+    let reload = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&reload))?;
+
+    let summarize = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGUSR1, Arc::clone(&summarize))?;
 
-    input.udev_assign_seat("seat0").unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&stop))?;
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&stop))?;
 
-    while poll(&mut [PollFd::new(&input, PollFlags::IN)], None).is_ok() {
-        input.dispatch().unwrap();
-        for event in &mut input {
-            handle(&event, &mut sway, &mut state_machine)?;
+    let mut seats = Vec::new();
+    for seat in &config.seats {
+        // --socket, then the config's socket, is the default for seats
+        // without a socket of their own.
+        let mut sways = vec![connect(
+            seat.socket.as_ref().or(args.socket.as_ref()),
+            &config,
+        )?];
+        for mirror in &seat.mirror {
+            sways.push(
+                connect(Some(mirror), &config)
+                    .with_context(|| format!("Failed to connect to {}", mirror.display()))?,
+            );
         }
+
+        let seat = Seat::open(&seat.name, sways, &config, &bindings)?;
+        if seat.devices.is_empty() {
+            if args.require_device {
+                anyhow::bail!("No gesture-capable device found on {}", seat.name);
+            }
+            warn!(
+                "No gesture-capable device found on {}, gestora will wait for one",
+                seat.name
+            );
+        }
+        seats.push(seat);
     }
 
-    Ok(())
+    let audit = match &args.audit_log {
+        Some(path) => Some(
+            AuditLog::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        ),
+        None => None,
+    };
+    if let Some(audit) = &audit {
+        for seat in &mut seats {
+            seat.sink.set_audit(audit.clone());
+        }
+    }
+    let mut extra_sinks = if args.dbus {
+        vec![dbus_sink()?]
+    } else {
+        Vec::new()
+    };
+
+    #[cfg(feature = "watch")]
+    let mut watcher = config_watcher();
+
+    let result = loop {
+        // Wake up in time to repeat a held swipe, preview one that rested or
+        // write out the audit log.
+        let timeout = seats
+            .iter()
+            .map(Seat::deadline)
+            .chain([audit.as_ref().and_then(AuditLog::flush_deadline)])
+            .flatten()
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+            .and_then(|timeout| Timespec::try_from(timeout).ok());
+
+        // A signal interrupting poll is not a reason to stop.
+        let mut fds: Vec<PollFd> = seats
+            .iter()
+            .map(|seat| PollFd::new(&seat.input, PollFlags::IN))
+            .collect();
+        #[cfg(feature = "watch")]
+        fds.extend(
+            watcher
+                .as_ref()
+                .map(|watcher| PollFd::new(watcher, PollFlags::IN)),
+        );
+        match poll(&mut fds, timeout.as_ref()) {
+            Ok(_) | Err(rustix::io::Errno::INTR) => {}
+            Err(_) => break Ok(()),
+        }
+
+        if stop.load(Ordering::Relaxed) {
+            info!("Shutting down");
+            break Ok(());
+        }
+
+        if summarize.swap(false, Ordering::Relaxed) {
+            if config.measure_latency {
+                for seat in &seats {
+                    info!("Latency on {}: {}", seat.name, seat.sink.latency());
+                }
+            } else {
+                info!("Latency isn't measured, set measure_latency = true to measure it");
+            }
+        }
+
+        if let Err(err) = seats
+            .iter_mut()
+            .try_for_each(|seat| seat.dispatch(&config, &mut extra_sinks))
+        {
+            break Err(err);
+        }
+
+        #[cfg(feature = "watch")]
+        if let Some(watcher) = &mut watcher {
+            match watcher.changed() {
+                Ok(changed) => {
+                    if changed {
+                        reload.store(true, Ordering::Relaxed);
+                    }
+                }
+                Err(err) => warn!("Failed to watch the config: {}", err),
+            }
+        }
+
+        // Wait for the fingers to lift, so a gesture isn't recognized with
+        // half the old config and half the new one.
+        if !seats.iter().any(|seat| seat.recognizer.in_gesture())
+            && reload.swap(false, Ordering::Relaxed)
+        {
+            match load_config() {
+                Ok((reloaded_config, reloaded_bindings)) => {
+                    config = Arc::new(reloaded_config);
+                    let reloaded_bindings = Arc::new(reloaded_bindings);
+                    for seat in &mut seats {
+                        seat.set_config(&config, &reloaded_bindings);
+                    }
+                    info!("Reloaded config");
+                }
+                Err(err) => error!("Keeping previous config: {}", err),
+            }
+        }
+
+        extra_sinks.flush();
+        if let Some(audit) = &audit {
+            audit.flush();
+        }
+
+        // A worker only stops on an error. The seat's other sways carry on
+        // without it, and gestora stops once a seat has none left.
+        if let Err(err) = seats
+            .iter_mut()
+            .try_for_each(|seat| seat.sink.drop_stopped())
+        {
+            break Err(err.into());
+        }
+    };
+
+    for seat in seats {
+        seat.sink.shutdown()?;
+    }
+    result
 }
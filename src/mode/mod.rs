@@ -0,0 +1,50 @@
+use gestora::sway::{Event, Sway, SwayError};
+use log::{debug, warn};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The binding mode sway is in, kept up to date by a thread that listens to
+/// mode events on a connection of its own.
+#[derive(Clone, Default)]
+pub(crate) struct Mode(Arc<Mutex<Option<String>>>);
+
+impl Mode {
+    /// Starts following the binding mode of the sway that `sway` is
+    /// connected to.
+    pub fn follow(sway: &Sway) -> Result<Mode, SwayError> {
+        let sway = sway.connect_again()?;
+
+        let mode = Mode::default();
+        mode.set(sway.get_binding_state()?);
+        let mut events = sway.subscribe(&["mode"])?;
+
+        let following = mode.clone();
+        thread::spawn(move || {
+            loop {
+                match events.read_event() {
+                    Ok(Event::Mode(name)) => {
+                        debug!("Binding mode is now {}", name);
+                        following.set(name);
+                    }
+                    Ok(Event::Other(_)) => {}
+                    Err(err) => {
+                        warn!("Stopped following the binding mode: {}", err);
+                        following.set("default".to_string());
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(mode)
+    }
+
+    fn set(&self, name: String) {
+        *self.0.lock().unwrap() = (name != "default").then_some(name);
+    }
+
+    /// The name of the binding mode, or `None` in the default mode.
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,129 @@
+use crate::config::{self, Config, Templates, Trigger};
+use anyhow::Context;
+use gestora::gesture::{SwipeStateMachine, Template};
+use gestora::sink::{GestureSink, PrintSink};
+use std::path::Path;
+
+/// A gesture event as written by `libinput record --with-libinput`, e.g.
+/// `- {time: 1.234567, type: GESTURE_SWIPE_UPDATE, nfingers: 3, delta: [ 1.50, -0.25], ...}`.
+#[derive(Debug)]
+enum RecordedSwipe {
+    Begin { finger_count: i32 },
+    Update { dx: f64, dy: f64 },
+    End,
+}
+
+/// Returns the raw value of `key` in a YAML flow mapping line.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(&format!("{}: ", key))? + key.len() + 2;
+    let value = &line[start..];
+
+    if value.starts_with('[') {
+        value.find(']').map(|end| &value[..=end])
+    } else {
+        value.find([',', '}']).map(|end| value[..end].trim())
+    }
+}
+
+fn parse_event(line: &str) -> Option<RecordedSwipe> {
+    match field(line, "type")? {
+        "GESTURE_SWIPE_BEGIN" => {
+            let fingers = field(line, "nfingers").or_else(|| field(line, "fingers"))?;
+            Some(RecordedSwipe::Begin {
+                finger_count: fingers.parse().ok()?,
+            })
+        }
+        "GESTURE_SWIPE_UPDATE" => {
+            let delta = field(line, "delta")?;
+            let (dx, dy) = delta.trim_matches(['[', ']']).split_once(',')?;
+            Some(RecordedSwipe::Update {
+                dx: dx.trim().parse().ok()?,
+                dy: dy.trim().parse().ok()?,
+            })
+        }
+        "GESTURE_SWIPE_END" => Some(RecordedSwipe::End),
+        _ => None,
+    }
+}
+
+/// Feeds the gesture events of a `libinput record` file through the swipe
+/// state machine and prints how each swipe is classified.
+///
+/// libinput cannot be driven from a recording through `LibinputInterface`,
+/// so this reads the libinput events the recording already contains. Record
+/// with `libinput record --with-libinput` to get them.
+pub fn replay(path: &Path) -> anyhow::Result<()> {
+    let recording = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let config = Config::load()?;
+    let mut state_machine = SwipeStateMachine::new(config.recognition);
+    state_machine.set_templates(config.templates);
+    let mut sink = PrintSink::new();
+
+    for event in recording.lines().filter_map(parse_event) {
+        match event {
+            RecordedSwipe::Begin { finger_count } => state_machine.begin(finger_count),
+            RecordedSwipe::Update { dx, dy } => state_machine.update(dx, dy),
+            RecordedSwipe::End => match state_machine.end() {
+                Ok(swipe) => sink.handle(&swipe),
+                Err(rejection) => println!("Rejected swipe: {}", rejection),
+            },
+        }
+    }
+
+    if sink.swipes() == 0 {
+        println!("No swipes found, was the recording made with --with-libinput?");
+    }
+
+    Ok(())
+}
+
+/// Learns the swipes of a `libinput record` file as templates for swipes in
+/// `direction`, and adds them to the templates next to the config.
+pub fn learn(direction: &str, path: &Path) -> anyhow::Result<()> {
+    let Trigger::Swipe(dir) = config::parse_direction(direction)? else {
+        anyhow::bail!("Only swipes can be learned, not {}", direction);
+    };
+    let recording = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let templates_path = config::templates_path()
+        .ok_or_else(|| anyhow::anyhow!("Can't find the config directory"))?;
+
+    let config = Config::load()?;
+    let mut state_machine = SwipeStateMachine::new(config.recognition);
+    let mut templates = Templates::load(&templates_path)?;
+    let mut learned = 0;
+
+    for event in recording.lines().filter_map(parse_event) {
+        match event {
+            RecordedSwipe::Begin { finger_count } => state_machine.begin(finger_count),
+            RecordedSwipe::Update { dx, dy } => state_machine.update(dx, dy),
+            RecordedSwipe::End => {
+                if let Err(rejection) = state_machine.end() {
+                    println!("Skipping swipe: {}", rejection);
+                } else if let Some(template) = Template::learn(dir, state_machine.path()) {
+                    templates.templates.push(template);
+                    learned += 1;
+                }
+            }
+        }
+    }
+
+    if learned == 0 {
+        anyhow::bail!("No swipes found, was the recording made with --with-libinput?");
+    }
+
+    if let Some(parent) = templates_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&templates_path, toml::to_string(&templates)?)
+        .with_context(|| format!("Failed to write {}", templates_path.display()))?;
+    println!(
+        "Learned {} swipes as {} in {}",
+        learned,
+        dir.name(),
+        templates_path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use super::GestureSink;
+use crate::gesture::Swipe;
+use log::warn;
+use zbus::blocking::Connection;
+
+/// The well-known name gestora takes on the session bus.
+pub const BUS_NAME: &str = "org.gestora.Gestora";
+pub const OBJECT_PATH: &str = "/org/gestora/Gestora";
+pub const INTERFACE: &str = "org.gestora.Gestora";
+
+/// Emits a `GesturePerformed(direction, fingers)` signal on the session bus
+/// for every swipe, e.g. `GesturePerformed("north", 3)`.
+pub struct DbusSink {
+    connection: Connection,
+}
+
+impl DbusSink {
+    pub fn connect() -> zbus::Result<Self> {
+        let connection = Connection::session()?;
+        connection.request_name(BUS_NAME)?;
+
+        Ok(DbusSink { connection })
+    }
+}
+
+impl GestureSink for DbusSink {
+    fn handle(&mut self, swipe: &Swipe) {
+        let result = self.connection.emit_signal(
+            None::<&str>,
+            OBJECT_PATH,
+            INTERFACE,
+            "GesturePerformed",
+            &(swipe.dir.name(), swipe.finger_count),
+        );
+
+        if let Err(err) = result {
+            warn!("Failed to emit GesturePerformed: {}", err);
+        }
+    }
+}
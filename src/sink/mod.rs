@@ -0,0 +1,145 @@
+#[cfg(feature = "dbus")]
+mod dbus;
+
+use crate::gesture::{Pinch, Swipe};
+
+#[cfg(feature = "dbus")]
+pub use dbus::DbusSink;
+
+/// Something that reacts to recognized swipes, such as running the bound
+/// sway commands or printing them.
+pub trait GestureSink {
+    fn handle(&mut self, swipe: &Swipe);
+
+    /// Called while the fingers are down with where the swipe is heading,
+    /// when previews are on. The swipe is later handled or the preview
+    /// cancelled.
+    fn preview(&mut self, _swipe: &Swipe) {}
+
+    /// Called when a previewed swipe won't be handled after all.
+    fn cancel_preview(&mut self) {}
+
+    fn handle_pinch(&mut self, _pinch: &Pinch) {}
+
+    /// Called regularly between swipes, so sinks that buffer can write out.
+    fn flush(&mut self) {}
+}
+
+impl<S: GestureSink + ?Sized> GestureSink for Box<S> {
+    fn handle(&mut self, swipe: &Swipe) {
+        (**self).handle(swipe);
+    }
+
+    fn preview(&mut self, swipe: &Swipe) {
+        (**self).preview(swipe);
+    }
+
+    fn cancel_preview(&mut self) {
+        (**self).cancel_preview();
+    }
+
+    fn handle_pinch(&mut self, pinch: &Pinch) {
+        (**self).handle_pinch(pinch);
+    }
+
+    fn flush(&mut self) {
+        (**self).flush();
+    }
+}
+
+/// Hands every swipe to each sink in turn.
+impl<S: GestureSink> GestureSink for Vec<S> {
+    fn handle(&mut self, swipe: &Swipe) {
+        for sink in self {
+            sink.handle(swipe);
+        }
+    }
+
+    fn preview(&mut self, swipe: &Swipe) {
+        for sink in self {
+            sink.preview(swipe);
+        }
+    }
+
+    fn cancel_preview(&mut self) {
+        for sink in self {
+            sink.cancel_preview();
+        }
+    }
+
+    fn handle_pinch(&mut self, pinch: &Pinch) {
+        for sink in self {
+            sink.handle_pinch(pinch);
+        }
+    }
+
+    fn flush(&mut self) {
+        for sink in self {
+            sink.flush();
+        }
+    }
+}
+
+/// A sink that may not be configured.
+impl<S: GestureSink> GestureSink for Option<S> {
+    fn handle(&mut self, swipe: &Swipe) {
+        if let Some(sink) = self {
+            sink.handle(swipe);
+        }
+    }
+
+    fn preview(&mut self, swipe: &Swipe) {
+        if let Some(sink) = self {
+            sink.preview(swipe);
+        }
+    }
+
+    fn cancel_preview(&mut self) {
+        if let Some(sink) = self {
+            sink.cancel_preview();
+        }
+    }
+
+    fn handle_pinch(&mut self, pinch: &Pinch) {
+        if let Some(sink) = self {
+            sink.handle_pinch(pinch);
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(sink) = self {
+            sink.flush();
+        }
+    }
+}
+
+/// Prints every swipe to stdout.
+#[derive(Default)]
+pub struct PrintSink {
+    swipes: usize,
+}
+
+impl PrintSink {
+    pub fn new() -> Self {
+        PrintSink { swipes: 0 }
+    }
+
+    /// How many swipes have been printed.
+    pub fn swipes(&self) -> usize {
+        self.swipes
+    }
+}
+
+impl GestureSink for PrintSink {
+    fn handle(&mut self, swipe: &Swipe) {
+        self.swipes += 1;
+        println!(
+            "{} fingers {}, distance {:.0}, jitter {:.1}, confidence {:.2}",
+            swipe.finger_count,
+            swipe.dir.name(),
+            swipe.distance,
+            swipe.jitter,
+            swipe.confidence
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use super::{
+    ByteOrder, Framing, HEADER_LEN, Magic, SwayError, Workspace, encode_message,
+    get_sway_socketpath, parse_reply, payload_length,
+};
+use serde_json::Value;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+/// A tokio flavour of [`super::Sway`] for async applications.
+pub struct AsyncSway {
+    stream: UnixStream,
+    framing: Framing,
+}
+
+impl AsyncSway {
+    /// Asks sway where its socket is, which briefly blocks on running
+    /// `sway --get-socketpath`.
+    pub async fn new() -> Result<AsyncSway, SwayError> {
+        let socket_path = get_sway_socketpath()?;
+        Self::connect_to(socket_path).await
+    }
+
+    /// Connects to the IPC socket at `path` without asking sway where it lives.
+    pub async fn connect_to(path: impl AsRef<Path>) -> Result<AsyncSway, SwayError> {
+        let stream = UnixStream::connect(path).await?;
+
+        Ok(AsyncSway {
+            stream,
+            framing: Framing::default(),
+        })
+    }
+
+    pub fn set_byte_order(&mut self, byte_order: ByteOrder) {
+        self.framing.byte_order = byte_order;
+    }
+
+    pub fn set_magic(&mut self, magic: Magic) {
+        self.framing.magic = magic;
+    }
+
+    pub async fn get_workspaces(&mut self) -> Result<Vec<Workspace>, SwayError> {
+        let workspaces = self.send_command(1, "").await?;
+        Ok(serde_json::from_value(workspaces)?)
+    }
+
+    pub async fn get_marks(&mut self) -> Result<Vec<String>, SwayError> {
+        let marks = self.send_command(5, "").await?;
+        Ok(serde_json::from_value(marks)?)
+    }
+
+    pub async fn run_command(&mut self, command: &str) -> Result<(), SwayError> {
+        self.send_command(0, command).await?;
+        Ok(())
+    }
+
+    async fn send_command(&mut self, command_type: u32, command: &str) -> Result<Value, SwayError> {
+        self.stream
+            .write_all(&encode_message(self.framing, command_type, command))
+            .await?;
+
+        let mut header = [0u8; HEADER_LEN];
+        self.stream.read_exact(&mut header).await?;
+
+        let mut payload = vec![0u8; payload_length(self.framing, &header)?];
+        self.stream.read_exact(&mut payload).await?;
+
+        parse_reply(&payload)
+    }
+}
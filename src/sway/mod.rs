@@ -1,21 +1,29 @@
+#[cfg(feature = "async")]
+mod asynchronous;
+
+use log::info;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::cell::RefCell;
 use std::io::{Error, ErrorKind};
 use std::io::{Read, Write};
 use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncSway;
+
 pub fn get_sway_socketpath() -> Result<String, Error> {
     let output = Command::new("sway").arg("--get-socketpath").output()?;
 
     if !output.status.success() {
-        return Err(Error::new(
-            ErrorKind::Other,
-            format!(
-                "sway --get-socketpath failed with exit code: {}",
-                output.status
-            ),
-        ));
+        return Err(Error::other(format!(
+            "sway --get-socketpath failed with exit code: {}",
+            output.status
+        )));
     }
 
     let socketpath = String::from_utf8(output.stdout)
@@ -36,97 +44,681 @@ pub fn get_sway_socketpath() -> Result<String, Error> {
 #[derive(Error, Debug)]
 pub enum SwayError {
     #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
+    IoError(std::io::Error),
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
     #[error("Sway IPC error: {0}")]
     IpcError(String),
+    #[error("Timed out waiting for sway")]
+    Timeout,
+    #[error("Connection to sway was lost")]
+    ConnectionLost,
+    /// No workspace has the focus, which can happen for a moment, e.g.
+    /// while an output is added.
+    #[error("No workspace is focused")]
+    NoFocusedWorkspace,
+    #[error("Unexpected response from sway: {0}")]
+    UnexpectedResponse(String),
+    /// Sway hung up before answering a command. The connection is back,
+    /// but whether the command ran is unknown, so it isn't sent again.
+    #[error("Sway hung up before answering, the command may not have run")]
+    Reconnected,
 }
 
-pub(crate) struct Sway {
-    stream: UnixStream,
+impl From<std::io::Error> for SwayError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => SwayError::Timeout,
+            ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::UnexpectedEof => SwayError::ConnectionLost,
+            _ => SwayError::IoError(err),
+        }
+    }
+}
+
+/// A node of the tree returned by `get_tree`.
+#[derive(Deserialize, Debug)]
+pub struct Node {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub node_type: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Wayland app id of a window, unset for Xwayland windows.
+    #[serde(default)]
+    pub app_id: Option<String>,
+    /// Workspace number, only set on workspaces.
+    pub num: Option<i64>,
+    #[serde(default)]
+    pub focused: bool,
+    /// Ids of the children, most recently focused first.
+    #[serde(default)]
+    pub focus: Vec<i64>,
+    #[serde(default)]
+    pub fullscreen_mode: u8,
+    #[serde(default)]
+    pub inhibit_idle: bool,
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub floating_nodes: Vec<Node>,
+}
+
+impl Node {
+    fn children(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().chain(&self.floating_nodes)
+    }
+
+    /// Whether this node or any node below it matches `predicate`.
+    pub fn any(&self, predicate: &impl Fn(&Node) -> bool) -> bool {
+        predicate(self) || self.children().any(|child| child.any(predicate))
+    }
+
+    /// The focused node at or below this node.
+    pub fn focused_node(&self) -> Option<&Node> {
+        if self.focused {
+            return Some(self);
+        }
+
+        self.children().find_map(Node::focused_node)
+    }
+
+    /// The output that holds the focused node.
+    pub fn focused_output(&self) -> Option<&Node> {
+        if self.node_type == "output" {
+            return self.any(&|node| node.focused).then_some(self);
+        }
+
+        self.nodes.iter().find_map(Node::focused_output)
+    }
+
+    /// The workspace that holds the node with the given id.
+    pub fn workspace_of(&self, id: i64) -> Option<&Node> {
+        if self.node_type == "workspace" {
+            return self.any(&|node| node.id == id).then_some(self);
+        }
+
+        self.nodes.iter().find_map(|node| node.workspace_of(id))
+    }
+
+    /// The windows below this node in tree order, tiling before floating
+    /// ones, leaving out the scratchpad.
+    pub fn windows(&self) -> Vec<&Node> {
+        let mut windows = Vec::new();
+        self.collect_windows(&mut windows);
+        windows
+    }
+
+    fn collect_windows<'a>(&'a self, windows: &mut Vec<&'a Node>) {
+        if self.node_type == "output" && self.name.as_deref() == Some("__i3") {
+            return;
+        }
+
+        let is_window = matches!(self.node_type.as_str(), "con" | "floating_con")
+            && self.nodes.is_empty()
+            && self.floating_nodes.is_empty();
+        if is_window {
+            windows.push(self);
+        }
+
+        for child in self.children() {
+            child.collect_windows(windows);
+        }
+    }
+
+    /// The workspace an output currently shows.
+    pub fn visible_workspace(&self) -> Option<&Node> {
+        let id = self.focus.first()?;
+        self.nodes.iter().find(|node| node.id == *id)
+    }
+}
+
+/// A workspace as returned by `get_workspaces`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Workspace {
+    /// The workspace number, -1 for workspaces whose name isn't a number.
+    pub num: i64,
+    pub name: String,
+    pub focused: bool,
+    pub visible: bool,
+    pub output: String,
+}
+
+/// An output as returned by `get_outputs`.
+#[derive(Deserialize, Debug)]
+pub struct Output {
+    pub name: String,
+    /// Whether the output is enabled and shows anything.
+    pub active: bool,
+    pub rect: Rect,
+}
+
+/// Position and size in the layout.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Rect {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+/// A seat as returned by `get_seats`.
+#[derive(Deserialize, Debug)]
+pub struct Seat {
+    pub name: String,
+    /// Id of the node the seat has focused, 0 if none.
+    pub focus: i64,
+    pub devices: Vec<InputDevice>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InputDevice {
+    /// `vendor:product:name`, the identifier sway uses in `input` commands.
+    pub identifier: String,
+}
+
+/// The version of sway as returned by `get_version`.
+#[derive(Deserialize, Debug)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// E.g. `1.10`, with the commit for builds from git.
+    pub human_readable: String,
+}
+
+/// Byte order of the length and type in i3-ipc headers. Sway uses the
+/// native one; the others help with tools that got it wrong.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ByteOrder {
+    #[default]
+    Native,
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    fn encode(self, value: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::Native => value.to_ne_bytes(),
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+
+    fn decode(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            ByteOrder::Native => u32::from_ne_bytes(bytes),
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// The magic string that starts every i3-ipc message. It is `i3-ipc` unless
+/// a compatible compositor uses another one.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Magic([u8; 6]);
+
+impl Default for Magic {
+    fn default() -> Self {
+        Magic(*b"i3-ipc")
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("IPC magic must be exactly 6 bytes, not {0:?}")]
+pub struct InvalidMagic(String);
+
+impl TryFrom<String> for Magic {
+    type Error = InvalidMagic;
+
+    fn try_from(magic: String) -> Result<Self, Self::Error> {
+        match magic.as_bytes().try_into() {
+            Ok(bytes) => Ok(Magic(bytes)),
+            Err(_) => Err(InvalidMagic(magic)),
+        }
+    }
+}
+
+impl From<Magic> for String {
+    fn from(magic: Magic) -> Self {
+        String::from_utf8_lossy(&magic.0).into_owned()
+    }
+}
+
+/// The command that switches to a workspace, with `{num}` or `{}` where the
+/// workspace number goes, e.g. `workspace {}` for workspaces named after
+/// their number.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct WorkspaceCommand(String);
+
+impl Default for WorkspaceCommand {
+    fn default() -> Self {
+        WorkspaceCommand("workspace number {num}".to_string())
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Workspace command {0:?} has no {{num}} or {{}} for the workspace number")]
+pub struct InvalidWorkspaceCommand(String);
+
+impl TryFrom<String> for WorkspaceCommand {
+    type Error = InvalidWorkspaceCommand;
+
+    fn try_from(command: String) -> Result<Self, Self::Error> {
+        if command.contains("{num}") || command.contains("{}") {
+            Ok(WorkspaceCommand(command))
+        } else {
+            Err(InvalidWorkspaceCommand(command))
+        }
+    }
+}
+
+impl From<WorkspaceCommand> for String {
+    fn from(command: WorkspaceCommand) -> Self {
+        command.0
+    }
+}
+
+impl WorkspaceCommand {
+    /// The command that switches to `workspace`.
+    pub fn command(&self, workspace: usize) -> String {
+        let workspace = workspace.to_string();
+        self.0
+            .replace("{num}", &workspace)
+            .replace("{}", &workspace)
+    }
+}
+
+/// How messages are framed on a connection.
+#[derive(Debug, Clone, Copy, Default)]
+struct Framing {
+    byte_order: ByteOrder,
+    magic: Magic,
+}
+
+/// A connection to sway's IPC socket. Reconnects once when sway closes it,
+/// e.g. after a restart of sway.
+pub struct Sway {
+    path: PathBuf,
+    stream: RefCell<UnixStream>,
+    framing: Framing,
+    /// The last `get_workspaces` reply and when it arrived.
+    workspaces: RefCell<Option<(Instant, Vec<Workspace>)>>,
+    workspaces_ttl: Duration,
+    workspace_command: WorkspaceCommand,
+}
+
+/// How long a `get_workspaces` reply is reused by default.
+pub const WORKSPACES_TTL: Duration = Duration::from_millis(50);
+/// How long sway gets to take a message or answer it before
+/// [`SwayError::Timeout`].
+const IPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Connects to the IPC socket at `path`, giving up on a sway that hangs
+/// after [`IPC_TIMEOUT`].
+fn connect_stream(path: &Path) -> Result<UnixStream, SwayError> {
+    let stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(IPC_TIMEOUT))?;
+    stream.set_write_timeout(Some(IPC_TIMEOUT))?;
+    Ok(stream)
 }
 
 impl Sway {
-    pub fn new() -> Result<Sway, Error> {
+    pub fn new() -> Result<Sway, SwayError> {
         let socket_path = get_sway_socketpath()?;
-        let stream = UnixStream::connect(&socket_path)?;
+        Self::connect_to(socket_path)
+    }
+
+    /// Connects to the IPC socket at `path` without asking sway where it lives.
+    pub fn connect_to(path: impl AsRef<Path>) -> Result<Sway, SwayError> {
+        let path = path.as_ref().to_path_buf();
+        let stream = connect_stream(&path)?;
+
+        Ok(Sway {
+            path,
+            stream: RefCell::new(stream),
+            framing: Framing::default(),
+            workspaces: RefCell::new(None),
+            workspaces_ttl: WORKSPACES_TTL,
+            workspace_command: WorkspaceCommand::default(),
+        })
+    }
 
-        Ok(Sway { stream })
+    /// Opens another connection to the same socket, framed the same way.
+    pub fn connect_again(&self) -> Result<Sway, SwayError> {
+        let mut sway = Sway::connect_to(&self.path)?;
+        sway.framing = self.framing;
+        sway.workspaces_ttl = self.workspaces_ttl;
+        sway.workspace_command = self.workspace_command.clone();
+        Ok(sway)
     }
 
-    pub fn get_active_workspace(&self) -> Result<usize, anyhow::Error> {
-        let workspaces: Value = self.send_command(1, "get_workspaces")?;
+    pub fn set_byte_order(&mut self, byte_order: ByteOrder) {
+        self.framing.byte_order = byte_order;
+    }
 
-        for workspace in workspaces.as_array().unwrap() {
-            if workspace["focused"].as_bool().unwrap_or(false) {
-                return workspace["num"]
-                    .as_u64()
-                    .map(|n| n as usize)
-                    .ok_or_else(|| {
-                        SwayError::IpcError("Invalid workspace number".to_string()).into()
-                    });
+    pub fn set_magic(&mut self, magic: Magic) {
+        self.framing.magic = magic;
+    }
+
+    /// Sets the command [`Sway::set_active_workspace`] sends.
+    pub fn set_workspace_command(&mut self, command: WorkspaceCommand) {
+        self.workspace_command = command;
+    }
+
+    pub fn workspace_command(&self) -> &WorkspaceCommand {
+        &self.workspace_command
+    }
+
+    /// Sets how long a `get_workspaces` reply is reused, so a burst of swipes
+    /// doesn't ask sway the same thing over and over. Zero disables it.
+    pub fn set_workspaces_ttl(&mut self, ttl: Duration) {
+        self.workspaces_ttl = ttl;
+    }
+
+    pub fn get_workspaces(&self) -> Result<Vec<Workspace>, SwayError> {
+        if let Some((at, workspaces)) = &*self.workspaces.borrow()
+            && at.elapsed() < self.workspaces_ttl
+        {
+            return Ok(workspaces.clone());
+        }
+
+        let workspaces: Vec<Workspace> =
+            serde_json::from_value(self.send_command(1, "get_workspaces")?)?;
+        if !self.workspaces_ttl.is_zero() {
+            *self.workspaces.borrow_mut() = Some((Instant::now(), workspaces.clone()));
+        }
+        Ok(workspaces)
+    }
+
+    pub fn get_active_workspace(&self) -> Result<usize, SwayError> {
+        let workspaces = self.get_workspaces()?;
+        let workspace = workspaces
+            .iter()
+            .find(|workspace| workspace.focused)
+            .ok_or(SwayError::NoFocusedWorkspace)?;
+
+        usize::try_from(workspace.num)
+            .map_err(|_| SwayError::UnexpectedResponse("Invalid workspace number".to_string()))
+    }
+
+    /// The focused workspace of the seat `device` belongs to. Falls back to
+    /// the globally focused workspace when the device isn't known or there is
+    /// only one seat.
+    pub fn get_seat_workspace(&self, device: Option<&str>) -> Result<usize, SwayError> {
+        let Some(device) = device else {
+            return self.get_active_workspace();
+        };
+
+        let seats = self.get_seats()?;
+        let seat = seats
+            .iter()
+            .find(|seat| seat.devices.iter().any(|input| input.identifier == device));
+
+        match seat {
+            Some(seat) if seats.len() > 1 && seat.focus != 0 => {
+                let tree = self.get_tree()?;
+                tree.workspace_of(seat.focus)
+                    .and_then(|workspace| workspace.num)
+                    .and_then(|num| usize::try_from(num).ok())
+                    .ok_or(SwayError::NoFocusedWorkspace)
             }
+            _ => self.get_active_workspace(),
         }
+    }
+
+    /// The output showing the focused workspace of the seat `device`
+    /// belongs to, as for [`Sway::get_seat_workspace`].
+    pub fn get_seat_output(&self, device: Option<&str>) -> Result<String, SwayError> {
+        let workspace = self.get_seat_workspace(device)?;
+        self.get_workspaces()?
+            .into_iter()
+            .find(|candidate| usize::try_from(candidate.num) == Ok(workspace))
+            .map(|workspace| workspace.output)
+            .ok_or_else(|| SwayError::IpcError(format!("No output shows workspace {}", workspace)))
+    }
+
+    pub fn get_outputs(&self) -> Result<Vec<Output>, SwayError> {
+        let outputs = self.send_command(3, "")?;
+        Ok(serde_json::from_value(outputs)?)
+    }
+
+    pub fn get_seats(&self) -> Result<Vec<Seat>, SwayError> {
+        let seats = self.send_command(101, "")?;
+        Ok(serde_json::from_value(seats)?)
+    }
+
+    pub fn set_active_workspace(&mut self, workspace: usize) -> Result<(), SwayError> {
+        let command = self.workspace_command.command(workspace);
+        self.run_command(&command)
+    }
+
+    pub fn get_tree(&self) -> Result<Node, SwayError> {
+        let tree = self.send_command(4, "")?;
+        Ok(serde_json::from_value(tree)?)
+    }
+
+    pub fn get_version(&self) -> Result<Version, SwayError> {
+        let version = self.send_command(7, "")?;
+        Ok(serde_json::from_value(version)?)
+    }
+
+    /// All marks in use. GET_MARKS is message type 5; 8 is GET_BINDING_MODES.
+    pub fn get_marks(&self) -> Result<Vec<String>, SwayError> {
+        let marks = self.send_command(5, "")?;
+        Ok(serde_json::from_value(marks)?)
+    }
+
+    /// Sends a tick with `payload` to every client subscribed to tick events.
+    pub fn send_tick(&mut self, payload: &str) -> Result<(), SwayError> {
+        self.send_command(10, payload)?;
+        Ok(())
+    }
+
+    /// The name of the binding mode sway is in, e.g. `default`.
+    pub fn get_binding_state(&self) -> Result<String, SwayError> {
+        let state = self.send_command(12, "")?;
+        state["name"].as_str().map(str::to_string).ok_or_else(|| {
+            SwayError::UnexpectedResponse("Binding state without a name".to_string())
+        })
+    }
+
+    /// Turns this connection into a stream of `events`, e.g. `["mode"]`.
+    /// A subscribed connection can't send commands anymore.
+    pub fn subscribe(self, events: &[&str]) -> Result<Subscription, SwayError> {
+        let events = serde_json::to_string(events)?;
+        self.send_command(2, &events)?;
 
-        Err(SwayError::IpcError("No active workspace found".to_string()).into())
+        // Events can take any time to come.
+        let stream = self.stream.into_inner();
+        stream.set_read_timeout(None)?;
+        Ok(Subscription {
+            stream,
+            framing: self.framing,
+        })
     }
 
-    pub fn set_active_workspace(&mut self, workspace: usize) -> Result<(), anyhow::Error> {
-        self.send_command(0, &format!("workspace number {}", workspace))?;
+    pub fn run_command(&mut self, command: &str) -> Result<(), SwayError> {
+        // Any command may change the workspaces.
+        self.workspaces.take();
+        self.send_command(0, command)?;
         Ok(())
     }
 
-    fn send_command(&self, command_type: u32, command: &str) -> Result<Value, anyhow::Error> {
-        // Create the IPC message
-        let payload = command.as_bytes();
-
-        let header = [
-            // Magic string
-            b"i3-ipc",
-            // Message length
-            &(payload.len() as u32).to_ne_bytes()[0..4],
-            // Message type
-            &command_type.to_ne_bytes(),
-        ];
-
-        // Write the message
-        let mut stream = self.stream.try_clone()?;
-        stream.write_all(&header.concat())?;
-        stream.write_all(payload)?;
-        stream.flush()?;
-
-        // Read the response header
-        let mut header = [0u8; 14];
-        stream.read_exact(&mut header)?;
-
-        // Verify magic string
-        if &header[0..6] != b"i3-ipc" {
-            return Err(SwayError::IpcError("Invalid magic string in response".to_string()).into());
-        }
-
-        // Get payload length
-        let length = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
-
-        // Read payload
-        let mut payload = vec![0u8; length];
-        stream.read_exact(&mut payload)?;
-
-        // Parse JSON response
-        let response: Value = serde_json::from_slice(&payload)?;
-
-        if let Some(success) = response["success"].as_bool() {
-            if !success {
-                return Err(SwayError::IpcError(
-                    response["error"]
-                        .as_str()
-                        .unwrap_or("Unknown error")
-                        .to_string(),
-                )
-                .into());
+    /// Sends a message and reads the reply, connecting again if sway hung up.
+    /// A message that sway may have got is only sent again when it doesn't
+    /// change anything, so a lost reply never runs a command twice.
+    fn send_command(&self, command_type: u32, command: &str) -> Result<Value, SwayError> {
+        let mut stream = self.stream.borrow_mut();
+        let result = self.exchange(&mut stream, command_type, command);
+        if let Err(SwayError::Timeout) = result {
+            // A late reply, or the rest of one, would be taken for the reply
+            // to the next message.
+            *stream = connect_stream(&self.path)?;
+        }
+        result
+    }
+
+    /// [`Sway::send_command`] up to giving up on a sway that timed out.
+    fn exchange(
+        &self,
+        stream: &mut UnixStream,
+        command_type: u32,
+        command: &str,
+    ) -> Result<Value, SwayError> {
+        let message = encode_message(self.framing, command_type, command);
+        let reconnect = |stream: &mut UnixStream| -> Result<(), SwayError> {
+            *stream = connect_stream(&self.path)?;
+            info!("Reconnected to sway");
+            Ok(())
+        };
+
+        if let Err(err) = write_message(stream, &message) {
+            if !matches!(err, SwayError::ConnectionLost) {
+                return Err(err);
+            }
+            reconnect(stream)?;
+            write_message(stream, &message)?;
+        }
+
+        match read_reply(stream, self.framing) {
+            Err(SwayError::ConnectionLost) => {
+                reconnect(stream)?;
+                if changes_state(command_type) {
+                    return Err(SwayError::Reconnected);
+                }
+                write_message(stream, &message)?;
+                read_reply(stream, self.framing)
+            }
+            result => result,
+        }
+    }
+}
+
+/// Events sway sends to a connection after [`Sway::subscribe`].
+pub struct Subscription {
+    stream: UnixStream,
+    framing: Framing,
+}
+
+/// An event that arrived on a [`Subscription`].
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// Sway switched to the binding mode with this name.
+    Mode(String),
+    /// Any event gestora doesn't look into, by its type.
+    Other(u32),
+}
+
+/// Event types have the highest bit set; the mode event is 2.
+const MODE_EVENT: u32 = 0x8000_0002;
+
+impl Subscription {
+    /// Waits for the next event.
+    pub fn read_event(&mut self) -> Result<Event, SwayError> {
+        let mut header = [0u8; HEADER_LEN];
+        self.stream.read_exact(&mut header)?;
+
+        let mut payload = vec![0u8; payload_length(self.framing, &header)?];
+        self.stream.read_exact(&mut payload)?;
+
+        match self
+            .framing
+            .byte_order
+            .decode(header[10..14].try_into().unwrap())
+        {
+            MODE_EVENT => {
+                let event: Value = serde_json::from_slice(&payload)?;
+                event["change"]
+                    .as_str()
+                    .map(|mode| Event::Mode(mode.to_string()))
+                    .ok_or_else(|| {
+                        SwayError::UnexpectedResponse("Mode event without a change".to_string())
+                    })
             }
+            other => Ok(Event::Other(other)),
         }
+    }
+}
+
+/// Whether a message of this type does something, like RUN_COMMAND and
+/// SEND_TICK, rather than only asking sway about its state.
+fn changes_state(message_type: u32) -> bool {
+    matches!(message_type, 0 | 10)
+}
+
+fn write_message(stream: &mut UnixStream, message: &[u8]) -> Result<(), SwayError> {
+    stream.write_all(message)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Reads the reply to the message sent last.
+fn read_reply(stream: &mut UnixStream, framing: Framing) -> Result<Value, SwayError> {
+    let mut header = [0u8; HEADER_LEN];
+    stream.read_exact(&mut header)?;
+
+    let mut payload = vec![0u8; payload_length(framing, &header)?];
+    stream.read_exact(&mut payload)?;
+
+    parse_reply(&payload)
+}
+
+/// Length of an i3-ipc header: the magic string, payload length and type.
+const HEADER_LEN: usize = 14;
+
+/// Frames `payload` as an i3-ipc message of `message_type`.
+fn encode_message(framing: Framing, message_type: u32, payload: &str) -> Vec<u8> {
+    let payload = payload.as_bytes();
+    let mut message = Vec::with_capacity(HEADER_LEN + payload.len());
 
-        Ok(response)
+    message.extend_from_slice(&framing.magic.0);
+    message.extend_from_slice(&framing.byte_order.encode(payload.len() as u32));
+    message.extend_from_slice(&framing.byte_order.encode(message_type));
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Checks the header of a reply and returns the length of its payload.
+fn payload_length(framing: Framing, header: &[u8; HEADER_LEN]) -> Result<usize, SwayError> {
+    if header[0..6] != framing.magic.0 {
+        return Err(SwayError::UnexpectedResponse(
+            "Invalid magic string in response".to_string(),
+        ));
     }
+
+    Ok(framing.byte_order.decode(header[6..10].try_into().unwrap()) as usize)
+}
+
+/// Parses the payload of a reply, turning failed commands into errors.
+fn parse_reply(payload: &[u8]) -> Result<Value, SwayError> {
+    let response: Value = serde_json::from_slice(payload)?;
+
+    // RUN_COMMAND replies with one result per command, other messages
+    // with a single object.
+    let results = match response.as_array() {
+        Some(results) => results.as_slice(),
+        None => std::slice::from_ref(&response),
+    };
+
+    for result in results {
+        if let Some(false) = result["success"].as_bool() {
+            return Err(SwayError::IpcError(
+                result["error"]
+                    .as_str()
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+            ));
+        }
+    }
+
+    Ok(response)
 }
@@ -0,0 +1,175 @@
+//! Watches the config file with inotify through rustix, which gestora already
+//! polls its other file descriptors with. The `notify` crate would bring a
+//! thread and a channel of its own into a loop that only needs to know when
+//! one file was saved.
+
+use rustix::fs::inotify::{self, CreateFlags, ReadFlags, WatchFlags};
+use rustix::io::Errno;
+use std::ffi::OsString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Room for a few events at a time. One takes at most 16 bytes and a file
+/// name of up to 255, and [`ConfigWatcher::changed`] reads until none are
+/// left.
+const EVENT_BUFFER: usize = 4096;
+
+/// Watches the config file through inotify, so saving it reloads the config.
+/// The directory is watched rather than the file, since editors often write
+/// a new file and rename it over the old one.
+pub(crate) struct ConfigWatcher {
+    inotify: OwnedFd,
+    dir: PathBuf,
+    file_name: OsString,
+    /// The watch on `dir`, unless it was deleted or moved away and there is
+    /// no new one to watch yet.
+    watch: Option<i32>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> io::Result<ConfigWatcher> {
+        let Some(file_name) = path.file_name() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file", path.display()),
+            ));
+        };
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let inotify = inotify::init(CreateFlags::NONBLOCK | CreateFlags::CLOEXEC)?;
+        let watch = watch_dir(&inotify, dir)?;
+
+        Ok(ConfigWatcher {
+            inotify,
+            dir: dir.to_path_buf(),
+            file_name: file_name.to_os_string(),
+            watch: Some(watch),
+        })
+    }
+
+    /// Whether the config was written since the last call. Doesn't block.
+    /// Once the directory is replaced, e.g. by a dotfiles manager, the new
+    /// one is watched from the next call on that finds it.
+    pub fn changed(&mut self) -> io::Result<bool> {
+        let mut buf = [MaybeUninit::uninit(); EVENT_BUFFER];
+        let mut events = inotify::Reader::new(&self.inotify, &mut buf);
+
+        let mut changed = false;
+        let mut replaced = false;
+        loop {
+            match events.next() {
+                Ok(event) => {
+                    replaced |= event
+                        .events()
+                        .intersects(ReadFlags::DELETE_SELF | ReadFlags::MOVE_SELF);
+                    changed |= event
+                        .file_name()
+                        .is_some_and(|name| name.to_bytes() == self.file_name.as_bytes());
+                }
+                Err(Errno::WOULDBLOCK) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if replaced && let Some(watch) = self.watch.take() {
+            // A moved directory is still watched where it went, a deleted
+            // one isn't anymore.
+            let _ = inotify::remove_watch(&self.inotify, watch);
+        }
+        if self.watch.is_none()
+            && let Ok(watch) = watch_dir(&self.inotify, &self.dir)
+        {
+            // The config came along with the new directory.
+            self.watch = Some(watch);
+            changed = true;
+        }
+        Ok(changed)
+    }
+}
+
+fn watch_dir(inotify: &OwnedFd, dir: &Path) -> io::Result<i32> {
+    Ok(inotify::add_watch(
+        inotify,
+        dir,
+        WatchFlags::CLOSE_WRITE
+            | WatchFlags::MOVED_TO
+            | WatchFlags::DELETE_SELF
+            | WatchFlags::MOVE_SELF,
+    )?)
+}
+
+impl AsFd for ConfigWatcher {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inotify.as_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own for a test, removed however the test ends.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(test: &str) -> TempDir {
+            let dir =
+                std::env::temp_dir().join(format!("gestora-watch-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn only_writes_to_the_config_count() {
+        let temp = TempDir::new("writes");
+        let dir = &temp.0;
+        let path = dir.join("config.toml");
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+
+        std::fs::write(dir.join("templates.toml"), "").unwrap();
+        assert!(!watcher.changed().unwrap());
+
+        // Like an editor saving through a temporary file.
+        std::fs::write(dir.join("config.toml.tmp"), "launcher = \"wofi\"").unwrap();
+        std::fs::rename(dir.join("config.toml.tmp"), &path).unwrap();
+        assert!(watcher.changed().unwrap());
+        assert!(!watcher.changed().unwrap());
+    }
+
+    #[test]
+    fn keeps_watching_a_replaced_directory() {
+        let temp = TempDir::new("replaced");
+        let dir = temp.0.join("gestora");
+        let moved = temp.0.join("gestora.old");
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+
+        std::fs::rename(&dir, &moved).unwrap();
+        assert!(!watcher.changed().unwrap());
+
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(&path, "").unwrap();
+        assert!(watcher.changed().unwrap());
+
+        // Writes in the old directory don't count anymore, the new one's do.
+        std::fs::write(moved.join("config.toml"), "").unwrap();
+        assert!(!watcher.changed().unwrap());
+        std::fs::write(&path, "launcher = \"wofi\"").unwrap();
+        assert!(watcher.changed().unwrap());
+    }
+}
@@ -0,0 +1,1029 @@
+use crate::action::{Action, ActionState, Gesture};
+use crate::audit::AuditLog;
+use crate::config::{Bindings, Config, Focus};
+use crate::latency::{Latency, Summary};
+use crate::mode::Mode;
+use gestora::gesture::{Pinch, Swipe};
+use gestora::sink::GestureSink;
+use gestora::sway::{Node, Sway, SwayError};
+use log::{debug, warn};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SendError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Work for the [`Worker`].
+pub(crate) enum Job {
+    /// Looks up the actions bound to a swipe and runs them, or only
+    /// previews them while the fingers are still down.
+    Swipe {
+        swipe: Swipe,
+        preview: bool,
+        mode: Option<String>,
+        bindings: Arc<Bindings>,
+        config: Arc<Config>,
+        audit: Option<AuditLog>,
+    },
+    /// Undoes the last preview.
+    CancelPreview,
+    /// Looks up the actions bound to a pinch and runs them.
+    Pinch {
+        pinch: Pinch,
+        mode: Option<String>,
+        bindings: Arc<Bindings>,
+        config: Arc<Config>,
+        audit: Option<AuditLog>,
+    },
+}
+
+/// Runs actions on a thread of its own, so a slow sway never keeps the input
+/// loop from reading events.
+pub(crate) struct Worker {
+    sender: Sender<Job>,
+    handle: JoinHandle<Result<(), SwayError>>,
+    latency: Latency,
+}
+
+impl Worker {
+    pub fn spawn(sway: Sway) -> Worker {
+        let (sender, receiver) = mpsc::channel();
+        let latency = Latency::default();
+        let measured = latency.clone();
+        let handle = thread::spawn(move || run(sway, receiver, measured));
+
+        Worker {
+            sender,
+            handle,
+            latency,
+        }
+    }
+
+    /// How long the swipes took so far, with `measure_latency` set.
+    pub fn latency(&self) -> Summary {
+        self.latency.summary()
+    }
+
+    /// Queues `job`. Fails once the worker has stopped on an error, which
+    /// [`Worker::shutdown`] returns. The job is dropped then.
+    pub fn send(&self, job: Job) -> Result<(), SendError<()>> {
+        self.sender.send(job).map_err(|_| SendError(()))
+    }
+
+    /// Waits for the worker to finish the queued jobs.
+    pub fn shutdown(self) -> Result<(), SwayError> {
+        drop(self.sender);
+        self.handle
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+/// Runs the actions bound to each swipe through sway, on the [`Worker`].
+pub(crate) struct SwaySink {
+    worker: Worker,
+    config: Arc<Config>,
+    bindings: Arc<Bindings>,
+    mode: Mode,
+    audit: Option<AuditLog>,
+    stopped: bool,
+}
+
+impl SwaySink {
+    pub fn new(sway: Sway, config: Arc<Config>, bindings: Arc<Bindings>, mode: Mode) -> SwaySink {
+        SwaySink {
+            worker: Worker::spawn(sway),
+            config,
+            bindings,
+            mode,
+            audit: None,
+            stopped: false,
+        }
+    }
+
+    /// Records the actions run for every gesture to `audit`.
+    pub fn set_audit(&mut self, audit: AuditLog) {
+        self.audit = Some(audit);
+    }
+
+    pub fn set_config(&mut self, config: Arc<Config>, bindings: Arc<Bindings>) {
+        self.config = config;
+        self.bindings = bindings;
+    }
+
+    /// Whether the worker stopped on an error, which
+    /// [`SwaySink::shutdown`] returns.
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn latency(&self) -> Summary {
+        self.worker.latency()
+    }
+
+    pub fn shutdown(self) -> Result<(), SwayError> {
+        self.worker.shutdown()
+    }
+}
+
+impl Job {
+    /// Until when to hold back a swipe that a binding with a higher `repeat`
+    /// might match once it's made again, so that only that binding runs.
+    fn hold_until(&self) -> Option<Instant> {
+        let Job::Swipe {
+            swipe,
+            preview: false,
+            bindings,
+            config,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        let within = Duration::from_millis(config.repeat_within_ms?);
+        bindings
+            .repeats_further(swipe.finger_count, swipe.dir, swipe.repeat_count)
+            .then(|| Instant::now() + within)
+    }
+
+    /// Whether this is the `held` swipe made once more.
+    fn repeats(&self, held: &Job) -> bool {
+        match (self, held) {
+            (
+                Job::Swipe {
+                    swipe,
+                    preview: false,
+                    ..
+                },
+                Job::Swipe { swipe: held, .. },
+            ) => {
+                swipe.dir == held.dir
+                    && swipe.finger_count == held.finger_count
+                    && swipe.repeat_count > held.repeat_count
+            }
+            _ => false,
+        }
+    }
+}
+
+impl SwaySink {
+    fn send(&mut self, job: Job) {
+        self.stopped |= self.worker.send(job).is_err();
+    }
+
+    fn send_swipe(&mut self, swipe: &Swipe, preview: bool) {
+        self.send(Job::Swipe {
+            swipe: swipe.clone(),
+            preview,
+            mode: self.mode.get(),
+            bindings: Arc::clone(&self.bindings),
+            config: Arc::clone(&self.config),
+            audit: self.audit.clone(),
+        });
+    }
+}
+
+impl GestureSink for SwaySink {
+    fn handle(&mut self, swipe: &Swipe) {
+        self.send_swipe(swipe, false);
+    }
+
+    fn preview(&mut self, swipe: &Swipe) {
+        self.send_swipe(swipe, true);
+    }
+
+    fn cancel_preview(&mut self) {
+        self.send(Job::CancelPreview);
+    }
+
+    fn handle_pinch(&mut self, pinch: &Pinch) {
+        self.send(Job::Pinch {
+            pinch: pinch.clone(),
+            mode: self.mode.get(),
+            bindings: Arc::clone(&self.bindings),
+            config: Arc::clone(&self.config),
+            audit: self.audit.clone(),
+        });
+    }
+}
+
+/// Hands gestures to several sways at once, e.g. to mirror them to a
+/// nested one. Each connection has a worker of its own, so a dead one
+/// doesn't hold up the others.
+pub(crate) struct Fanout {
+    sinks: Vec<SwaySink>,
+    audit: Option<AuditLog>,
+}
+
+impl Fanout {
+    pub fn new(sinks: Vec<SwaySink>) -> Fanout {
+        Fanout { sinks, audit: None }
+    }
+
+    /// Records the actions run for every gesture to `audit`, once: only the
+    /// first connection does, the others mirror it.
+    pub fn set_audit(&mut self, audit: AuditLog) {
+        self.audit = Some(audit);
+        self.pass_audit_on();
+    }
+
+    fn pass_audit_on(&mut self) {
+        if let (Some(audit), Some(sink)) = (&self.audit, self.sinks.first_mut()) {
+            sink.set_audit(audit.clone());
+        }
+    }
+
+    pub fn set_config(&mut self, config: &Arc<Config>, bindings: &Arc<Bindings>) {
+        for sink in &mut self.sinks {
+            sink.set_config(Arc::clone(config), Arc::clone(bindings));
+        }
+    }
+
+    /// Drops the connections whose worker stopped on an error. Fails with
+    /// the error of the last one, once none are left.
+    pub fn drop_stopped(&mut self) -> Result<(), SwayError> {
+        let (stopped, running): (Vec<SwaySink>, Vec<SwaySink>) = std::mem::take(&mut self.sinks)
+            .into_iter()
+            .partition(SwaySink::stopped);
+        self.sinks = running;
+        self.pass_audit_on();
+
+        for sink in stopped {
+            match sink.shutdown() {
+                Err(err) if !self.sinks.is_empty() => {
+                    warn!("Dropping a connection to sway: {}", err)
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// How long the swipes took on all the connections together.
+    pub fn latency(&self) -> Summary {
+        self.sinks
+            .iter()
+            .map(SwaySink::latency)
+            .fold(Summary::default(), Summary::merge)
+    }
+
+    pub fn shutdown(self) -> Result<(), SwayError> {
+        self.sinks.into_iter().try_for_each(SwaySink::shutdown)
+    }
+}
+
+impl GestureSink for Fanout {
+    fn handle(&mut self, swipe: &Swipe) {
+        self.sinks.handle(swipe);
+    }
+
+    fn preview(&mut self, swipe: &Swipe) {
+        self.sinks.preview(swipe);
+    }
+
+    fn cancel_preview(&mut self) {
+        self.sinks.cancel_preview();
+    }
+
+    fn handle_pinch(&mut self, pinch: &Pinch) {
+        self.sinks.handle_pinch(pinch);
+    }
+}
+
+fn run(mut sway: Sway, jobs: Receiver<Job>, latency: Latency) -> Result<(), SwayError> {
+    let mut state = ActionState::default();
+    // A swipe that may still be repeated, and until when it waits for that.
+    let mut held: Option<(Job, Instant)> = None;
+
+    loop {
+        let deadline = state
+            .mode_deadline()
+            .into_iter()
+            .chain(held.as_ref().map(|(_, until)| *until))
+            .min();
+        let job = match deadline {
+            Some(deadline) => {
+                match jobs.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(job) => job,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let now = Instant::now();
+                        if let Some((job, _)) = held.take_if(|(_, until)| *until <= now) {
+                            run_job(&mut sway, job, &mut state, &latency)?;
+                        }
+                        if state.mode_deadline().is_some_and(|until| until <= now) {
+                            state.leave_mode(&mut sway)?;
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match jobs.recv() {
+                Ok(job) => job,
+                Err(_) => break,
+            },
+        };
+        state.keep_mode();
+
+        if let Some((single, _)) = held.take() {
+            if job.repeats(&single) {
+                debug!("Swipe repeated, skipping the binding for doing it once");
+            } else {
+                run_job(&mut sway, single, &mut state, &latency)?;
+            }
+        }
+        match job.hold_until() {
+            Some(until) => held = Some((job, until)),
+            None => run_job(&mut sway, job, &mut state, &latency)?,
+        }
+    }
+
+    if let Some((job, _)) = held {
+        run_job(&mut sway, job, &mut state, &latency)?;
+    }
+    Ok(())
+}
+
+/// Runs `job`. Errors that leave the connection fine for the next job are
+/// only logged.
+fn run_job(
+    sway: &mut Sway,
+    job: Job,
+    state: &mut ActionState,
+    latency: &Latency,
+) -> Result<(), SwayError> {
+    let result = match job {
+        Job::Swipe {
+            swipe,
+            preview,
+            mode,
+            bindings,
+            config,
+            audit,
+        } => {
+            let gesture = Gesture::Swipe(&swipe);
+            let mode = mode.as_deref();
+            let result = run_gesture(sway, gesture, preview, mode, &bindings, &config, state);
+            if !preview {
+                state.end_preview();
+                if let (Some(audit), Ok(actions)) = (&audit, &result) {
+                    audit.record(gesture, actions);
+                }
+                // Only swipes sway ran actions for tell how fast it is.
+                if config.measure_latency
+                    && let Ok(actions) = &result
+                    && !actions.is_empty()
+                {
+                    let elapsed = swipe.recognized_at.elapsed();
+                    debug!("Swipe took {:?} from its end until sway ran it", elapsed);
+                    latency.record(elapsed);
+                }
+            }
+            result.map(|_| ())
+        }
+        Job::CancelPreview => state.cancel_preview(sway),
+        Job::Pinch {
+            pinch,
+            mode,
+            bindings,
+            config,
+            audit,
+        } => {
+            let gesture = Gesture::Pinch(&pinch);
+            let mode = mode.as_deref();
+            let result = run_gesture(sway, gesture, false, mode, &bindings, &config, state);
+            if let (Some(audit), Ok(actions)) = (&audit, &result) {
+                audit.record(gesture, actions);
+            }
+            result.map(|_| ())
+        }
+    };
+
+    match result {
+        // Sway gets back to focusing a workspace on its own.
+        Err(err @ SwayError::NoFocusedWorkspace) => debug!("Skipping gesture: {}", err),
+        // Sway refused a command, e.g. for a mark or window that is gone.
+        // The connection is still fine for the next gesture.
+        Err(SwayError::IpcError(err)) => warn!("Sway failed to run a gesture: {}", err),
+        // Sway restarted or stalled, the next gesture goes to the new
+        // connection.
+        Err(err @ (SwayError::Reconnected | SwayError::Timeout)) => {
+            warn!("Skipping gesture: {}", err)
+        }
+        result => result?,
+    }
+
+    Ok(())
+}
+
+/// Runs the actions bound to `gesture`, and returns those that ran.
+fn run_gesture<'a>(
+    sway: &mut Sway,
+    gesture: Gesture,
+    preview: bool,
+    mode: Option<&str>,
+    bindings: &'a Bindings,
+    config: &Config,
+    state: &mut ActionState,
+) -> Result<&'a [Action], SwayError> {
+    // Only ask sway for what bindings and config depend on.
+    let output = if bindings.has_output_bindings() {
+        Some(sway.get_seat_output(gesture.device())?)
+    } else {
+        None
+    };
+    let tree = if bindings.has_conditions()
+        || config.pause_when_presenting
+        || !config.inhibit_app_ids.is_empty()
+    {
+        Some(sway.get_tree()?)
+    } else {
+        None
+    };
+
+    let window = tree.as_ref().and_then(Node::focused_node);
+    let app_id = window.and_then(|window| window.app_id.as_deref());
+    let focus = Focus {
+        mode,
+        output: output.as_deref(),
+        app_id,
+        fullscreen: window.is_some_and(|window| window.fullscreen_mode != 0),
+    };
+    let Some(actions) = bindings.lookup(
+        gesture.finger_count(),
+        gesture.trigger(),
+        gesture.repeat_count(),
+        &focus,
+    ) else {
+        return Ok(&[]);
+    };
+
+    if config.pause_when_presenting && tree.as_ref().is_some_and(presenting) {
+        debug!("Ignoring swipe while presenting");
+        return Ok(&[]);
+    }
+
+    if let Some(app_id) = app_id
+        && config.inhibit_app_ids.iter().any(|id| id == app_id)
+    {
+        debug!("Ignoring swipe while {} is focused", app_id);
+        return Ok(&[]);
+    }
+
+    for action in actions {
+        debug!(
+            direction = gesture.name(),
+            fingers = gesture.finger_count(),
+            action:? = action;
+            "{} finger {} runs {:?}", gesture.finger_count(), gesture.name(), action
+        );
+        if preview {
+            action.preview(sway, gesture, config, state)?;
+        } else {
+            action.run(sway, gesture, config, state)?;
+        }
+    }
+
+    if !preview {
+        state.end_swipe(actions);
+    }
+    Ok(actions)
+}
+
+/// Whether the focused output shows a fullscreen window or something keeps it
+/// from idling, which usually means a presentation or a video call.
+fn presenting(tree: &Node) -> bool {
+    tree.focused_output()
+        .and_then(|output| output.visible_workspace())
+        .is_some_and(|workspace| {
+            workspace.any(&|node| node.fullscreen_mode != 0 || node.inhibit_idle)
+        })
+}
+
+#[cfg(test)]
+#[path = "../../tests/support/stub_sway.rs"]
+mod stub_sway;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gestora::gesture::{PinchDir, SwipeDir};
+    use serde_json::{Value, json};
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    const CONFIG: &str = r#"
+        [[binding]]
+        fingers = 3
+        direction = ["west", "east"]
+        actions = [{ command = "nop 3 $direction" }]
+
+        [[binding]]
+        fingers = "4+"
+        direction = "north"
+        actions = [{ command = "nop 4+ north" }]
+
+        # Never fires, 4+ above matches first.
+        [[binding]]
+        fingers = 5
+        direction = "north"
+        actions = [{ command = "nop 5 north" }]
+
+        [[binding]]
+        fingers = "2-3"
+        direction = "south"
+        actions = [{ command = "nop 2-3 south" }]
+    "#;
+
+    const DIRECTIONS: [SwipeDir; 8] = [
+        SwipeDir::N,
+        SwipeDir::S,
+        SwipeDir::W,
+        SwipeDir::E,
+        SwipeDir::NE,
+        SwipeDir::NW,
+        SwipeDir::SE,
+        SwipeDir::SW,
+    ];
+
+    fn sway_sink(name: &str, config: &str) -> (SwaySink, JoinHandle<Vec<String>>, PathBuf) {
+        sway_sink_with(name, config, stub_sway::succeed)
+    }
+
+    /// A sink for `config` whose sway answers with `reply`.
+    fn sway_sink_with(
+        name: &str,
+        config: &str,
+        reply: impl FnMut(u32, &str) -> Value + Send + 'static,
+    ) -> (SwaySink, JoinHandle<Vec<String>>, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("gestora-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = stub_sway::stub_sway(UnixListener::bind(&path).unwrap(), reply);
+
+        let config: Config = toml::from_str(config).unwrap();
+        let bindings = config.compile().unwrap();
+        let sway = Sway::connect_to(&path).unwrap();
+        let sink = SwaySink::new(sway, Arc::new(config), Arc::new(bindings), Mode::default());
+        (sink, server, path)
+    }
+
+    fn swipe(finger_count: i32, dir: SwipeDir) -> Swipe {
+        Swipe {
+            dir,
+            finger_count,
+            distance: 100.0,
+            device: None,
+            held: false,
+            streak: 0,
+            repeat_count: 1,
+            jitter: 0.0,
+            duration: Duration::from_millis(100),
+            recognized_at: Instant::now(),
+            began_usec: None,
+            ended_usec: None,
+            confidence: 1.0,
+        }
+    }
+
+    #[test]
+    fn finger_counts_dispatch_to_the_first_matching_binding() {
+        let (mut sink, server, path) = sway_sink("dispatch", CONFIG);
+
+        for finger_count in 2..=5 {
+            for dir in DIRECTIONS {
+                sink.handle(&swipe(finger_count, dir));
+            }
+        }
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "nop 2-3 south",
+                "nop 2-3 south",
+                "nop 3 west",
+                "nop 3 east",
+                "nop 4+ north",
+                "nop 4+ north",
+            ]
+        );
+    }
+
+    #[test]
+    fn gestures_without_a_focused_workspace_are_skipped() {
+        let (mut sink, server, path) = sway_sink(
+            "unfocused",
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = ["next-workspace"]
+
+            [[binding]]
+            fingers = 3
+            direction = "west"
+            actions = [{ command = "nop west" }]
+            "#,
+        );
+
+        sink.handle(&swipe(3, SwipeDir::E));
+        sink.handle(&swipe(3, SwipeDir::W));
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(server.join().unwrap(), ["nop west"]);
+    }
+
+    #[test]
+    fn failed_commands_do_not_stop_the_worker() {
+        let (mut sink, server, path) = sway_sink_with(
+            "failed",
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = [{ command = "focus mark gone" }]
+
+            [[binding]]
+            fingers = 3
+            direction = "west"
+            actions = [{ command = "nop west" }]
+            "#,
+            |message_type, payload| match payload {
+                "focus mark gone" => json!([{ "success": false, "error": "No mark gone" }]),
+                _ => stub_sway::succeed(message_type, payload),
+            },
+        );
+
+        sink.handle(&swipe(3, SwipeDir::E));
+        sink.handle(&swipe(3, SwipeDir::W));
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(server.join().unwrap(), ["focus mark gone", "nop west"]);
+    }
+
+    #[test]
+    fn repeated_swipes_only_run_the_repeat_binding() {
+        let (mut sink, server, path) = sway_sink(
+            "repeat",
+            r#"
+            repeat_within_ms = 400
+
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            repeat = 2
+            actions = [{ command = "nop twice" }]
+
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            actions = [{ command = "nop once" }]
+
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = [{ command = "nop east" }]
+            "#,
+        );
+        let repeated = |repeat_count| Swipe {
+            repeat_count,
+            ..swipe(3, SwipeDir::N)
+        };
+
+        sink.handle(&repeated(1));
+        sink.handle(&repeated(2));
+        // Another swipe runs the held one right away, shutting down too.
+        sink.handle(&repeated(1));
+        sink.handle(&swipe(3, SwipeDir::E));
+        // Once the window is over the held swipe runs, and a late repeat
+        // doesn't take its place anymore.
+        sink.handle(&repeated(1));
+        thread::sleep(Duration::from_millis(500));
+        sink.handle(&repeated(2));
+        sink.handle(&repeated(1));
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "nop twice",
+                "nop once",
+                "nop east",
+                "nop once",
+                "nop twice",
+                "nop once"
+            ]
+        );
+    }
+
+    #[test]
+    fn a_restarted_sway_does_not_stop_the_worker() {
+        let path =
+            std::env::temp_dir().join(format!("gestora-restarted-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        // Hangs up on the first command without answering, like a sway
+        // restarting, and answers on the next connection.
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            while let Some((message_type, payload)) = stub_sway::read_message(&mut stream) {
+                if message_type == stub_sway::RUN_COMMAND {
+                    break;
+                }
+                let answer = stub_sway::succeed(message_type, &payload).to_string();
+                stub_sway::write_message(&mut stream, message_type, &answer);
+            }
+            drop(stream);
+            stub_sway::stub_sway(listener, stub_sway::succeed)
+                .join()
+                .unwrap()
+        });
+
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let bindings = config.compile().unwrap();
+        let sway = Sway::connect_to(&path).unwrap();
+        let mut sink = SwaySink::new(sway, Arc::new(config), Arc::new(bindings), Mode::default());
+
+        sink.handle(&swipe(3, SwipeDir::E));
+        sink.handle(&swipe(3, SwipeDir::W));
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(server.join().unwrap(), ["nop 3 west"]);
+    }
+
+    #[test]
+    fn latency_counts_only_the_swipes_that_ran() {
+        let (mut sink, server, path) = sway_sink_with(
+            "latency",
+            r#"
+            measure_latency = true
+
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = [{ command = "nop east" }]
+
+            [[binding]]
+            fingers = 3
+            direction = "west"
+            actions = [{ command = "focus mark gone" }]
+            "#,
+            |message_type, payload| match payload {
+                "focus mark gone" => json!([{ "success": false, "error": "No mark gone" }]),
+                _ => stub_sway::succeed(message_type, payload),
+            },
+        );
+        let latency = sink.worker.latency.clone();
+
+        sink.handle(&swipe(3, SwipeDir::E));
+        sink.handle(&swipe(3, SwipeDir::W));
+        sink.handle(&swipe(3, SwipeDir::N));
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+        server.join().unwrap();
+
+        assert!(latency.summary().to_string().starts_with("1 swipes,"));
+    }
+
+    #[test]
+    fn audit_log_records_the_actions_that_ran() {
+        let (mut sink, server, path) = sway_sink(
+            "audit",
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = [{ command = "nop east" }]
+
+            [[binding]]
+            fingers = 3
+            direction = "west"
+            mode = "resize"
+            actions = [{ command = "nop west" }]
+
+            [[binding]]
+            fingers = 3
+            direction = "pinch-in"
+            actions = ["kill"]
+            "#,
+        );
+        let log_path = path.with_extension("log");
+        let _ = std::fs::remove_file(&log_path);
+        sink.set_audit(AuditLog::open(&log_path).unwrap());
+
+        sink.handle(&swipe(3, SwipeDir::E));
+        sink.handle(&swipe(3, SwipeDir::W));
+        sink.handle_pinch(&Pinch {
+            dir: PinchDir::In,
+            finger_count: 3,
+            scale: 0.5,
+            device: None,
+        });
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+        server.join().unwrap();
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        std::fs::remove_file(log_path).unwrap();
+        let actions: Vec<&str> = log
+            .lines()
+            .map(|line| line.split_once(" actions=").unwrap().1)
+            .collect();
+        // The west binding is for another binding mode, so nothing ran.
+        assert_eq!(
+            actions,
+            [r#"[{"command":"nop east"}]"#, "[]", r#"["kill"]"#]
+        );
+        assert!(log.lines().nth(2).unwrap().contains("direction=pinch-in"));
+    }
+
+    #[test]
+    fn pinches_widen_and_narrow_the_gaps() {
+        let (mut sink, server, path) = sway_sink(
+            "gaps",
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = ["pinch-in", "pinch-out"]
+            actions = [{ gaps = { delta = 5 } }]
+            "#,
+        );
+
+        for dir in [PinchDir::Out, PinchDir::In] {
+            sink.handle_pinch(&Pinch {
+                dir,
+                finger_count: 3,
+                scale: 1.0,
+                device: None,
+            });
+        }
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            server.join().unwrap(),
+            ["gaps inner current plus 5", "gaps inner current minus 5"]
+        );
+    }
+
+    #[test]
+    fn a_dead_connection_does_not_stop_the_others() {
+        let (live, server, path) = sway_sink("fanout", CONFIG);
+
+        // A sway that went away: the connection is never accepted and
+        // can't be made again.
+        let dead_path = path.with_extension("dead");
+        let listener = UnixListener::bind(&dead_path).unwrap();
+        let dead_sway = Sway::connect_to(&dead_path).unwrap();
+        drop(listener);
+        std::fs::remove_file(&dead_path).unwrap();
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let bindings = config.compile().unwrap();
+        let dead = SwaySink::new(
+            dead_sway,
+            Arc::new(config),
+            Arc::new(bindings),
+            Mode::default(),
+        );
+
+        let mut fanout = Fanout::new(vec![live, dead]);
+        let mut swipes = 0;
+        while fanout.sinks.len() > 1 && swipes < 100 {
+            fanout.handle(&swipe(3, SwipeDir::E));
+            fanout.drop_stopped().unwrap();
+            swipes += 1;
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(fanout.sinks.len(), 1);
+
+        fanout.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(server.join().unwrap(), vec!["nop 3 east"; swipes]);
+    }
+
+    #[test]
+    fn undo_goes_back_to_the_previous_workspace() {
+        // Keeps track of the focused workspace as it is switched.
+        let mut focused = 3;
+        let (mut sink, server, path) = sway_sink_with(
+            "undo",
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = ["next-workspace"]
+
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            actions = ["undo"]
+            "#,
+            move |message_type, payload| {
+                if message_type == stub_sway::GET_WORKSPACES {
+                    return json!([{
+                        "num": focused, "name": focused.to_string(),
+                        "focused": true, "visible": true, "output": "eDP-1"
+                    }]);
+                }
+                if let Some(workspace) = payload.strip_prefix("workspace number ") {
+                    focused = workspace.parse().unwrap();
+                }
+                stub_sway::succeed(message_type, payload)
+            },
+        );
+
+        for dir in [
+            SwipeDir::E,
+            SwipeDir::E,
+            SwipeDir::N,
+            SwipeDir::N,
+            SwipeDir::N,
+        ] {
+            sink.handle(&swipe(3, dir));
+        }
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "workspace number 4",
+                "workspace number 5",
+                "workspace number 4",
+                "workspace number 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn set_mode_goes_back_to_default_without_gestures() {
+        let (mut sink, server, path) = sway_sink(
+            "mode-timeout",
+            r#"
+            mode_timeout_ms = 50
+
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            actions = [{ set-mode = "resize" }]
+            "#,
+        );
+
+        sink.handle(&swipe(3, SwipeDir::N));
+        thread::sleep(Duration::from_millis(200));
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(server.join().unwrap(), [r#"mode "resize""#, "mode default"]);
+    }
+
+    #[test]
+    fn swap_workspaces_trades_with_the_other_output() {
+        // Shows workspace 1 on the focused eDP-1 and 2 on HDMI-A-1.
+        let (mut sink, server, path) = sway_sink_with(
+            "swap",
+            r#"
+            [[binding]]
+            fingers = 3
+            direction = "east"
+            actions = ["swap-workspaces"]
+
+            [[binding]]
+            fingers = 3
+            direction = "north"
+            actions = ["undo"]
+            "#,
+            |message_type, payload| match message_type {
+                stub_sway::GET_WORKSPACES => json!([
+                    {"num": 1, "name": "1", "focused": true, "visible": true, "output": "eDP-1"},
+                    {"num": 2, "name": "2", "focused": false, "visible": true, "output": "HDMI-A-1"}
+                ]),
+                stub_sway::GET_OUTPUTS => json!([
+                    {"name": "eDP-1", "active": true, "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}},
+                    {"name": "HDMI-A-1", "active": true, "rect": {"x": 1920, "y": 0, "width": 1920, "height": 1080}}
+                ]),
+                _ => stub_sway::succeed(message_type, payload),
+            },
+        );
+
+        sink.handle(&swipe(3, SwipeDir::E));
+        sink.handle(&swipe(3, SwipeDir::N));
+        sink.shutdown().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let swap = |fetch| {
+            format!(
+                "move workspace to output HDMI-A-1; workspace --no-auto-back-and-forth \"{}\"; move workspace to output eDP-1",
+                fetch
+            )
+        };
+        assert_eq!(server.join().unwrap(), [swap("2"), swap("1")]);
+    }
+}
@@ -0,0 +1,74 @@
+//! Replays the hand-written swipes in `fixtures/synthetic_swipes.txt` to
+//! check that recognition tuning keeps classifying them the same way. They
+//! are made up to look like touchpad swipes, not recorded from one.
+
+use gestora::gesture::{RecognitionConfig, SwipeDir, SwipeVector};
+
+const FIXTURES: &str = include_str!("fixtures/synthetic_swipes.txt");
+
+const DIRECTIONS: [SwipeDir; 8] = [
+    SwipeDir::N,
+    SwipeDir::S,
+    SwipeDir::W,
+    SwipeDir::E,
+    SwipeDir::NE,
+    SwipeDir::NW,
+    SwipeDir::SE,
+    SwipeDir::SW,
+];
+
+struct Fixture {
+    line: usize,
+    expected: SwipeDir,
+    updates: Vec<(f64, f64)>,
+}
+
+fn fixtures() -> Vec<Fixture> {
+    let mut fixtures: Vec<Fixture> = Vec::new();
+
+    for (i, line) in FIXTURES.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_once(' ') {
+            Some((dx, dy)) => fixtures
+                .last_mut()
+                .expect("updates before the first direction")
+                .updates
+                .push((dx.parse().unwrap(), dy.parse().unwrap())),
+            None => fixtures.push(Fixture {
+                line: i + 1,
+                expected: DIRECTIONS
+                    .into_iter()
+                    .find(|dir| dir.name() == line)
+                    .unwrap_or_else(|| panic!("Unknown direction {}", line)),
+                updates: Vec::new(),
+            }),
+        }
+    }
+
+    fixtures
+}
+
+#[test]
+fn synthetic_swipes_classify_as_expected() {
+    let recognition = RecognitionConfig::default();
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty());
+
+    for fixture in fixtures {
+        let mut swipe = SwipeVector::new();
+        for &(dx, dy) in &fixture.updates {
+            swipe.add_update(dx, dy, &recognition);
+        }
+
+        assert_eq!(
+            swipe.calculate_direction(&recognition),
+            fixture.expected,
+            "swipe on line {} of fixtures/synthetic_swipes.txt",
+            fixture.line
+        );
+    }
+}
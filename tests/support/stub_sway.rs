@@ -0,0 +1,67 @@
+//! A stub i3-ipc server, shared by the worker's tests and the IPC tests.
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::thread::{self, JoinHandle};
+
+pub const RUN_COMMAND: u32 = 0;
+pub const GET_WORKSPACES: u32 = 1;
+pub const SUBSCRIBE: u32 = 2;
+pub const GET_OUTPUTS: u32 = 3;
+pub const GET_VERSION: u32 = 7;
+pub const SEND_TICK: u32 = 10;
+pub const MODE_EVENT: u32 = 0x8000_0002;
+
+pub fn read_message(stream: &mut UnixStream) -> Option<(u32, String)> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).ok()?;
+    assert_eq!(&header[0..6], b"i3-ipc");
+
+    let length = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload).ok()?;
+    Some((message_type, String::from_utf8(payload).unwrap()))
+}
+
+pub fn write_message(stream: &mut UnixStream, message_type: u32, payload: &str) {
+    stream.write_all(b"i3-ipc").unwrap();
+    stream
+        .write_all(&(payload.len() as u32).to_ne_bytes())
+        .unwrap();
+    stream.write_all(&message_type.to_ne_bytes()).unwrap();
+    stream.write_all(payload.as_bytes()).unwrap();
+}
+
+/// Answers GET_WORKSPACES with no workspaces and everything else like a
+/// successful RUN_COMMAND.
+pub fn succeed(message_type: u32, _payload: &str) -> Value {
+    match message_type {
+        GET_WORKSPACES => Value::Array(Vec::new()),
+        _ => serde_json::json!([{ "success": true }]),
+    }
+}
+
+/// Accepts one connection and answers every message with what `reply`
+/// returns for its type and payload. Collects the commands and ticks it was
+/// sent until the client hangs up.
+pub fn stub_sway(
+    listener: UnixListener,
+    mut reply: impl FnMut(u32, &str) -> Value + Send + 'static,
+) -> JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut commands = Vec::new();
+        while let Some((message_type, payload)) = read_message(&mut stream) {
+            let answer = reply(message_type, &payload).to_string();
+            if matches!(message_type, RUN_COMMAND | SEND_TICK) {
+                commands.push(payload);
+            }
+            write_message(&mut stream, message_type, &answer);
+        }
+        commands
+    })
+}
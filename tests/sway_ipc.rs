@@ -0,0 +1,204 @@
+//! Talks to a stub i3-ipc server to check the framing end to end.
+#![cfg(feature = "sway")]
+
+mod support {
+    pub mod stub_sway;
+}
+
+use gestora::sway::{Event, Sway, SwayError};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::thread::JoinHandle;
+use support::stub_sway::{
+    GET_VERSION, GET_WORKSPACES, MODE_EVENT, RUN_COMMAND, SEND_TICK, SUBSCRIBE, read_message,
+    stub_sway, write_message,
+};
+
+const WORKSPACES: &str = r#"[
+    {"num": 1, "name": "1", "focused": false, "visible": false, "output": "eDP-1"},
+    {"num": 3, "name": "3", "focused": true, "visible": true, "output": "eDP-1"}
+]"#;
+
+const VERSION: &str = r#"{
+    "human_readable": "1.10", "variant": "sway", "major": 1, "minor": 10, "patch": 0,
+    "loaded_config_file_name": "/etc/sway/config"
+}"#;
+
+/// Serves canned replies on a fresh socket and collects every command it is
+/// asked to run.
+fn canned_sway(name: &str) -> (PathBuf, JoinHandle<Vec<String>>) {
+    let path = std::env::temp_dir().join(format!("gestora-{}-{}.sock", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let server = stub_sway(listener, |message_type, _| match message_type {
+        GET_WORKSPACES => serde_json::from_str(WORKSPACES).unwrap(),
+        GET_VERSION => serde_json::from_str(VERSION).unwrap(),
+        RUN_COMMAND | SEND_TICK => serde_json::json!([{ "success": true }]),
+        other => panic!("Unexpected message type {}", other),
+    });
+
+    (path, server)
+}
+
+#[test]
+fn active_workspace_comes_from_get_workspaces() {
+    let (path, _) = canned_sway("active");
+    let sway = Sway::connect_to(&path).unwrap();
+
+    assert_eq!(sway.get_active_workspace().unwrap(), 3);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn version_comes_from_get_version() {
+    let (path, _) = canned_sway("version");
+    let version = Sway::connect_to(&path).unwrap().get_version().unwrap();
+
+    assert_eq!((version.major, version.minor), (1, 10));
+    assert_eq!(version.human_readable, "1.10");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn set_active_workspace_runs_a_command() {
+    let (path, server) = canned_sway("set");
+    let mut sway = Sway::connect_to(&path).unwrap();
+
+    sway.set_active_workspace(4).unwrap();
+    drop(sway);
+    assert_eq!(server.join().unwrap(), ["workspace number 4"]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn send_tick_passes_the_payload_on() {
+    let (path, server) = canned_sway("tick");
+    let mut sway = Sway::connect_to(&path).unwrap();
+
+    sway.send_tick("gestora east").unwrap();
+    drop(sway);
+    assert_eq!(server.join().unwrap(), ["gestora east"]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reconnects_when_sway_closes_the_socket() {
+    let path = std::env::temp_dir().join(format!("gestora-reconnect-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let (commands, received) = mpsc::channel();
+
+    // Answers one command per connection, then hangs up.
+    thread::spawn(move || {
+        for connection in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (message_type, payload) = read_message(&mut stream).unwrap();
+            write_message(&mut stream, message_type, r#"[{"success": true}]"#);
+            drop(stream);
+            commands.send((connection, payload)).unwrap();
+        }
+    });
+
+    let mut sway = Sway::connect_to(&path).unwrap();
+    sway.run_command("workspace number 1").unwrap();
+    assert_eq!(
+        received.recv().unwrap(),
+        (0, "workspace number 1".to_string())
+    );
+
+    sway.run_command("workspace number 2").unwrap();
+    assert_eq!(
+        received.recv().unwrap(),
+        (1, "workspace number 2".to_string())
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn a_lost_reply_does_not_run_the_command_again() {
+    let path = std::env::temp_dir().join(format!("gestora-lost-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    // Hangs up on the first command without replying, then answers on the
+    // next connection.
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_message(&mut stream).unwrap();
+        drop(stream);
+        stub_sway(listener, |_, _| serde_json::json!([{ "success": true }]))
+            .join()
+            .unwrap()
+    });
+
+    let mut sway = Sway::connect_to(&path).unwrap();
+    assert!(matches!(
+        sway.run_command("workspace next"),
+        Err(SwayError::Reconnected)
+    ));
+    sway.run_command("workspace number 1").unwrap();
+    drop(sway);
+
+    assert_eq!(server.join().unwrap(), ["workspace number 1"]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn gives_up_on_a_sway_that_does_not_answer() {
+    let path = std::env::temp_dir().join(format!("gestora-hung-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    // Never answers the first connection, and answers the next one.
+    let server = thread::spawn(move || {
+        let (mut stalled, _) = listener.accept().unwrap();
+        read_message(&mut stalled).unwrap();
+        stub_sway(listener, |_, _| serde_json::json!([{ "success": true }]))
+            .join()
+            .unwrap()
+    });
+
+    let mut sway = Sway::connect_to(&path).unwrap();
+    assert!(matches!(
+        sway.run_command("workspace next"),
+        Err(SwayError::Timeout)
+    ));
+    // The next command goes to a new connection.
+    sway.run_command("workspace number 1").unwrap();
+    drop(sway);
+
+    assert_eq!(server.join().unwrap(), ["workspace number 1"]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn subscription_reports_mode_changes() {
+    let path = std::env::temp_dir().join(format!("gestora-mode-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let (message_type, payload) = read_message(&mut stream).unwrap();
+        assert_eq!((message_type, payload.as_str()), (SUBSCRIBE, r#"["mode"]"#));
+        write_message(&mut stream, SUBSCRIBE, r#"{"success": true}"#);
+        write_message(
+            &mut stream,
+            MODE_EVENT,
+            r#"{"change": "resize", "pango_markup": false}"#,
+        );
+    });
+
+    let mut events = Sway::connect_to(&path)
+        .unwrap()
+        .subscribe(&["mode"])
+        .unwrap();
+    assert_eq!(
+        events.read_event().unwrap(),
+        Event::Mode("resize".to_string())
+    );
+    std::fs::remove_file(path).unwrap();
+}